@@ -0,0 +1,101 @@
+use crate::chordnova::chord::{CNChord, CNChordExtendedData};
+use crate::chordnova::consonance::ConsonanceMeasure;
+use crate::chordnova::root::RootAlgorithm;
+use crate::chordnova::similarity::SimilarityMeasure;
+use crate::chordnova::tension::{SetharesRoughness, Spectrum, TensionModel};
+use crate::chordnova::voiceleading::VoiceLeading;
+
+/// Analyse chords of a progression
+///
+/// See also the analyser mode of the original C++ implementation.
+pub struct Analyser {
+    /// algorithm for r
+    pub root_algorithm: RootAlgorithm,
+    /// model for t
+    pub tension_model: Box<dyn TensionModel>,
+    /// measure for x, the similarity to the previous chord
+    pub similarity_measure: SimilarityMeasure,
+    /// extended consonance measures to fill in
+    pub consonance_measures: Vec<ConsonanceMeasure>,
+}
+
+impl Default for Analyser {
+    fn default() -> Self {
+        Analyser {
+            root_algorithm: RootAlgorithm::Hindemith,
+            tension_model: Box::new(SetharesRoughness { spectrum: Spectrum::default() }),
+            similarity_measure: SimilarityMeasure::Cosine,
+            consonance_measures: vec![],
+        }
+    }
+}
+
+impl Analyser {
+    /// Indicators of `chord`; the ones about motion are left at their defaults without `prev`
+    #[allow(dead_code)]
+    pub fn analyse(&self, chord: &CNChord, prev: Option<&CNChord>) -> CNChordExtendedData {
+        let mut data = CNChordExtendedData {
+            s_size: chord.get_distinct_pitch_classes().len() as i16,
+            tension: self.tension_model.tension(chord) as f32,
+            name_with_octave: Some(chord.to_string()),
+            ..Default::default()
+        };
+        if let (Some(lowest), Some(highest)) = (chord._pitches.iter().min(), chord._pitches.iter().max()) {
+            data.span = i16::from(highest.0) - i16::from(lowest.0);
+        }
+        if let Some(estimate) = self.root_algorithm.find_root(chord) {
+            data.root = i16::from(estimate.root.0);
+        }
+        for measure in &self.consonance_measures {
+            let value = measure.consonance(chord);
+            match measure {
+                ConsonanceMeasure::HarmonicEntropy(_) => data.harmonic_entropy = Some(value as f32),
+                ConsonanceMeasure::EulerGradus => data.gradus = Some(value as u32),
+                ConsonanceMeasure::Periodicity => data.periodicity = Some(value as u32),
+            }
+        }
+        if let Some(prev) = prev {
+            let similarity = self.similarity_measure.similarity(&prev.get_pitch_class_set(), &chord.get_pitch_class_set());
+            data.similarity = (100.0 * similarity).round() as i16;
+            data.common_note = prev.common_notes(chord) as i16;
+        }
+        if let Some(voice_leading) = prev.and_then(|prev| VoiceLeading::find(prev, chord, false).ok()) {
+            data.sv = voice_leading.diff.sv as i16;
+            data.vec = voice_leading.diff.diff_vec;
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn analyse1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let a_minor: CNChord = CNChord::from_str("E3 A3 C4").unwrap();
+        let analyser = Analyser { root_algorithm: RootAlgorithm::Parncutt, ..Default::default() };
+        let data = analyser.analyse(&a_minor, Some(&c_major));
+        assert_eq!(data.root, 9);
+        assert_eq!(data.span, 8);
+        assert_eq!(data.tension, SetharesRoughness { spectrum: Spectrum::default() }.tension(&a_minor) as f32);
+        // every voice moves down as written, not to the nearest inversion
+        assert_eq!(data.sv, 22);
+        assert_eq!(data.vec, vec![-8, -7, -7]);
+        assert_eq!(data.similarity, 100);
+        assert_eq!(data.common_note, 1);
+        assert_eq!(data.gradus, None);
+    }
+
+    #[test]
+    fn analyse2() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let analyser = Analyser { consonance_measures: vec![ConsonanceMeasure::EulerGradus, ConsonanceMeasure::Periodicity], ..Default::default() };
+        let data = analyser.analyse(&c_major, None);
+        assert_eq!(data.gradus, Some(9));
+        assert_eq!(data.periodicity, Some(4));
+        assert_eq!(data.harmonic_entropy, None);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::chordnova::chord::CNChord;
+use crate::chordnova::curve::MetricCurve;
+use crate::chordnova::generator::{Candidate, CNGenerator};
+use crate::chordnova::metric::MetricTarget;
+use crate::chordnova::progression::Progression;
+
+/// Parameters of the beam search
+pub struct BeamSearchConfig {
+    /// number of chords to find after the starting one
+    pub steps: usize,
+    /// number of partial progressions kept after every step
+    pub beam_width: usize,
+    /// number of complete progressions returned
+    pub results: usize,
+    /// cost of every semitone of sv
+    pub voice_leading_weight: f64,
+    /// curves the indicators should follow over the steps
+    pub targets: Vec<MetricTarget>,
+}
+
+impl Default for BeamSearchConfig {
+    fn default() -> Self {
+        BeamSearchConfig {
+            steps: 4,
+            beam_width: 16,
+            results: 4,
+            voice_leading_weight: 1.0,
+            targets: vec![],
+        }
+    }
+}
+
+/// Multi-step progression search, keeping the `beam_width` cheapest partial progressions after every step
+///
+/// A step costs its sv plus the weighted deviation from every target curve, plus the cost of the generator's prior if it has one;
+/// the generator's bounds and filters stay hard constraints.
+pub struct BeamSearch {
+    pub config: BeamSearchConfig,
+}
+
+impl BeamSearch {
+    #[allow(dead_code)]
+    pub fn new(config: BeamSearchConfig) -> Self {
+        BeamSearch { config }
+    }
+
+    /// cost of moving from `prev` to `candidate` at step `step`; `None` if it strays beyond the tolerance of a target
+    pub fn step_cost(&self, generator: &CNGenerator, prev: &CNChord, candidate: &Candidate, step: usize) -> Option<f64> {
+        let position = MetricCurve::position(step, self.config.steps);
+        let mut cost = self.config.voice_leading_weight * f64::from(candidate.voice_leading.diff.sv) + generator.prior_cost(prev, candidate);
+        for target in &self.config.targets {
+            let deviation = target.deviation(prev, candidate, position);
+            if target.tolerance.is_some_and(|tolerance| deviation > tolerance) {
+                return None;
+            }
+            cost += target.weight * deviation;
+        }
+        Some(cost)
+    }
+
+    /// the cheapest progressions of `steps` chords following `start`, each step being a candidate of `generator`
+    #[allow(dead_code)]
+    pub fn search(&self, generator: &CNGenerator, start: &CNChord) -> Vec<Progression> {
+        let mut beam = vec![Progression { chords: vec![start.clone()], cost: 0.0 }];
+        for step in 0..self.config.steps {
+            let mut expanded = beam.iter()
+                .flat_map(|progression| {
+                    let prev = progression.chords.last().unwrap();
+                    generator.generate(prev).into_iter().filter_map(move |candidate| {
+                        let cost = progression.cost + self.step_cost(generator, prev, &candidate, step)?;
+                        let mut chords = progression.chords.clone();
+                        chords.push(candidate.chord);
+                        Some(Progression { chords, cost })
+                    })
+                })
+                .collect::<Vec<Progression>>();
+            expanded.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+            expanded.truncate(self.config.beam_width);
+            beam = expanded;
+        }
+        beam.truncate(self.config.results);
+        beam
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::chordnova::generator::GeneratorConfig;
+    use crate::chordnova::markov::{MarkovModel, Prior};
+    use crate::chordnova::metric::Metric;
+    use crate::chordnova::pitch::Pitch;
+    use crate::chordnova::tension::{SetharesRoughness, Spectrum, TensionModel};
+
+    #[test]
+    fn search1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() });
+        let search = BeamSearch::new(BeamSearchConfig {
+            steps: 3,
+            beam_width: 8,
+            results: 3,
+            targets: vec![MetricTarget { metric: Metric::Tension(Box::new(SetharesRoughness { spectrum: Spectrum::default() })), curve: MetricCurve::constant(1.5), weight: 2.0, tolerance: None }],
+            ..Default::default()
+        });
+        let progressions = search.search(&generator, &c_major);
+        assert_eq!(progressions.len(), 3);
+        assert!(progressions.windows(2).all(|pair| pair[0].cost <= pair[1].cost));
+        assert!(progressions.iter().all(|progression| progression.chords.len() == 4 && progression.chords[0].to_string() == "C4, E4, G4"));
+        // greedily taking the cheapest step every time cannot do better
+        let mut greedy = Progression { chords: vec![c_major.clone()], cost: 0.0 };
+        for step in 0..3 {
+            let prev = greedy.chords.last().unwrap().clone();
+            let best = generator.generate(&prev).into_iter()
+                .map(|candidate| (search.step_cost(&generator, &prev, &candidate, step).unwrap(), candidate))
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .unwrap();
+            greedy.cost += best.0;
+            greedy.chords.push(best.1.chord);
+        }
+        assert!(progressions[0].cost <= greedy.cost);
+    }
+
+    #[test]
+    fn search2() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() });
+        // roughness rising and falling again, within 0.3 of the curve
+        let curve = MetricCurve::new(vec![(0.0, 0.9), (0.5, 1.5), (1.0, 0.9)]);
+        let roughness = SetharesRoughness { spectrum: Spectrum::default() };
+        let search = BeamSearch::new(BeamSearchConfig {
+            steps: 5,
+            beam_width: 8,
+            results: 1,
+            voice_leading_weight: 0.1,
+            targets: vec![MetricTarget { metric: Metric::Tension(Box::new(SetharesRoughness { spectrum: Spectrum::default() })), curve: curve.clone(), weight: 1.0, tolerance: Some(0.3) }],
+        });
+        let progression = &search.search(&generator, &c_major)[0];
+        assert_eq!(progression.chords.len(), 6);
+        for (step, chord) in progression.chords[1..].iter().enumerate() {
+            let tension = roughness.tension(chord);
+            assert!((tension - curve.value_at(MetricCurve::position(step, 5))).abs() <= 0.3);
+        }
+        assert!(roughness.tension(&progression.chords[3]) > roughness.tension(&progression.chords[1]));
+    }
+
+    #[test]
+    fn prior1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let search = BeamSearch::new(BeamSearchConfig { steps: 2, results: 1, ..Default::default() });
+        let chords = |generator: &CNGenerator| search.search(generator, &c_major)[0].chords.iter().map(|chord| chord.to_string()).collect::<Vec<String>>();
+        let config = GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() };
+        assert_eq!(chords(&CNGenerator::new(config)), vec!["C4, E4, G4"; 3]);
+        let mut model = MarkovModel::default();
+        model.train_text("C4 E4 G4 | C4 F4 A4 | C4 E4 G4").unwrap();
+        let config = GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, prior: Some(Prior { model, weight: 10.0 }), ..Default::default() };
+        assert_eq!(chords(&CNGenerator::new(config)), vec!["C4, E4, G4", "C4, F4, A4", "C4, E4, G4"]);
+    }
+}
@@ -10,9 +10,11 @@ use crate::chordnova::pitchparser::PitchParser;
 
 
 use std::fmt;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 use std::rc::Rc;
 use itertools::Itertools;
+use crate::chordnova::pcset::PitchClassSet;
 use crate::chordnova::pitch::{ParsePitchError, Pitch, PitchClass};
 use crate::chordnova::util::iterable_to_str;
 
@@ -40,7 +42,7 @@ pub struct CNChordExtendedData {
     pub tension: f32,
     /// h
     pub thickness: f32,
-    /// r
+    /// r; pitch class of the root, -1 if the chord has none
     pub root: i16,
     /// g
     pub g_center: i16,
@@ -48,7 +50,7 @@ pub struct CNChordExtendedData {
     pub span: i16,
     /// ss
     pub sspan: i16,
-    /// x
+    /// x; similarity to the previous chord in percent
     pub similarity: i16,
     /// kk
     pub _chroma_old: f32,
@@ -74,6 +76,92 @@ pub struct CNChordExtendedData {
     pub count_vec: Vec<i16>,
     /// reference chord to calculate chroma_old. This is to replace prev_chroma_old
     pub ref_chord: Option<Rc<CNChord>>,
+    /// harmonic entropy; extended, not in the original C++ implementation
+    pub harmonic_entropy: Option<f32>,
+    /// Euler's gradus suavitatis; extended
+    pub gradus: Option<u32>,
+    /// relative periodicity; extended
+    pub periodicity: Option<u32>,
+}
+
+/// Octave shifts and inversions tried by `CNChord::find_vec_in_window`
+pub struct InversionSearchWindow {
+    /// octave shifts applied to the new chord
+    pub octaves: RangeInclusive<i8>,
+    /// highest inversion to try; `None` tries every inversion
+    pub max_inversion: Option<usize>,
+}
+
+impl Default for InversionSearchWindow {
+    fn default() -> Self {
+        InversionSearchWindow {
+            octaves: -1..=0,
+            max_inversion: None,
+        }
+    }
+}
+
+/// Doubling and omission allowed by `CNChord::find_pitch_class_mapping`
+pub struct PitchClassMatchOptions {
+    /// number of pitch classes that may be left out
+    pub max_omissions: usize,
+    /// number of voices that may take a pitch class already taken by another voice
+    pub max_doublings: usize,
+    /// number of voices that may split into two pitch classes
+    pub max_splits: usize,
+}
+
+impl Default for PitchClassMatchOptions {
+    fn default() -> Self {
+        PitchClassMatchOptions {
+            max_omissions: 0,
+            max_doublings: usize::MAX,
+            max_splits: usize::MAX,
+        }
+    }
+}
+
+/// Pairing of the voices of two chords used by `CNChord::find_vec_with`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VoiceMatcher {
+    /// `CNChord::find_best_chord_pairs`; only voices of the smaller chord can be doubled
+    #[default]
+    ChordPairs,
+    /// `CNChord::find_best_voice_mapping` with at most this many splits and merges
+    VoiceMapping(usize),
+}
+
+impl Default for CNChordExtendedData {
+    fn default() -> Self {
+        CNChordExtendedData {
+            _voice_leading_max: 0,
+            s_size: 0,
+            tension: 0f32,
+            thickness: 0f32,
+            root: -1,
+            g_center: 0,
+            span: 0,
+            sspan: 0,
+            similarity: 0,
+            _chroma_old: 0f32,
+            chroma: 0f32,
+            q_indicator: 0f32,
+            common_note: 0,
+            sv: 0,
+            overflow_state: OverflowState::NoOverflow,
+            hide_octave: false,
+            name: None,
+            name_with_octave: None,
+            vec: vec![],
+            self_diff: vec![],
+            count_vec: vec![],
+            ref_chord: None,
+            harmonic_entropy: None,
+            gradus: None,
+            periodicity: None,
+        }
+    }
 }
 
 pub struct ChordDiff {
@@ -86,7 +174,7 @@ pub struct ChordDiff {
 
 impl Clone for ChordDiff {
     fn clone(&self) -> Self {
-        ChordDiff::new(self.diff_vec.iter().map(|x| *x).collect::<Vec<i16>>())
+        ChordDiff::new(self.diff_vec.to_vec())
     }
 }
 
@@ -103,12 +191,12 @@ impl ChordDiff {
 
     /// sum of (absolute value) of (diff) vector
     /// Measuring the distance of two chords in the original cpp ChordNova implementation
-    fn sv(diff_vec: &Vec<i16>) -> u16 {
-        u16::try_from(diff_vec.iter().map(|x| (*x).abs() as u32).sum::<u32>()).unwrap()
+    fn sv(diff_vec: &[i16]) -> u16 {
+        u16::try_from(diff_vec.iter().map(|x| u32::from(x.unsigned_abs())).sum::<u32>()).unwrap()
     }
 
     /// norm of the diff vector. Penalize large diff more.
-    fn norm(diff_vec: &Vec<i16>) -> f64 {
+    fn norm(diff_vec: &[i16]) -> f64 {
         (diff_vec.iter().map(|x| (*x as i32).pow(2)).sum::<i32>() as f64).sqrt()
     }
 
@@ -153,13 +241,13 @@ impl CNChord {
     /// See also
     ///     Chord(const vector<int>& _notes, double _chroma_old = 0.0);
     /// in original C++ implementation
-    pub fn from_notes(notes: &Vec<Pitch>, dedup: bool) -> CNChord {
+    pub fn from_notes(notes: &[Pitch], dedup: bool) -> CNChord {
         match dedup {
             true => CNChord {
-                _pitches: notes.into_iter().sorted().dedup().map(|pitch| *pitch).collect(),
+                _pitches: notes.iter().sorted().dedup().copied().collect(),
             },
             false => CNChord {
-                _pitches: notes.into_iter().sorted().map(|pitch| *pitch).collect(),
+                _pitches: notes.iter().sorted().copied().collect(),
             }
         }
     }
@@ -181,44 +269,166 @@ impl CNChord {
     //     return self._voice_leading_max;
     // }
 
-    /// traverse some inversions
+    /// traverse every inversion, one octave down or in place
     #[allow(dead_code)] // It is used in test cases
     pub fn find_vec_simple(&self, new_chord: &CNChord) -> Result<(CNChord, CNChord), ParseCNChordError> {
-        // Corresponding to the original implementation in c++
-        // 1. Consider all possible inversions.
-        // 2. Consider two octives
-        // 3. Always invert the second chord
-        let base = itertools::iproduct!((-1i8..1i8), (0..new_chord._pitches.len()));
-        match base.min_by_key(|inversion_map| {
-            match new_chord.apply_inversion(inversion_map.0, inversion_map.1) {
-                Ok(inverted_new_chord) => match self.diff(&inverted_new_chord) {
-                    Ok(p) => p.sv,
-                    Err(_) => u16::MAX
-                },
-                Err(_) => u16::MAX
+        self.find_vec_in_window(new_chord, &InversionSearchWindow::default())
+    }
+
+    /// traverse the inversions and octave shifts of `window`
+    ///
+    /// Corresponding to the original implementation in c++, but the octave range is configurable:
+    /// 1. Consider the inversions allowed by the window.
+    /// 2. Consider every octave shift in the window, starting from the one that lines the basses up.
+    /// 3. Always invert the second chord
+    ///
+    /// An octave shift is skipped once the distance between the basses or the top notes alone
+    /// cannot beat the best candidate so far, and the search in that direction stops as soon as
+    /// the basses alone cannot. Ties go to the lowest octave, then the lowest inversion.
+    pub fn find_vec_in_window(&self, new_chord: &CNChord, window: &InversionSearchWindow) -> Result<(CNChord, CNChord), ParseCNChordError> {
+        if self.t_size() == 0 || new_chord.t_size() == 0 {
+            return Err(ParseCNChordError { msg: String::from("Cannot find vec of an empty chord") });
+        }
+        if window.octaves.is_empty() {
+            return Err(ParseCNChordError { msg: format!("Empty octave range {:?}", window.octaves) });
+        }
+        let bass = i16::from(self._pitches[0].0);
+        let top = i16::from(self._pitches[self.t_size() - 1].0);
+        let inversions = match window.max_inversion {
+            Some(p) => (p + 1).min(new_chord.t_size()),
+            None => new_chord.t_size()
+        };
+        // sv, octave, inversion, shifted chord
+        let mut best: Option<(u16, i16, usize, CNChord)> = None;
+        for inversion in 0..inversions {
+            let inverted = new_chord.apply_inversion(0, inversion)?;
+            let new_bass = i16::from(inverted._pitches[0].0);
+            let new_top = i16::from(inverted._pitches[inverted.t_size() - 1].0);
+            let center = (f32::from(bass - new_bass) / 12f32).round() as i16;
+            let center = center.clamp(i16::from(*window.octaves.start()), i16::from(*window.octaves.end()));
+            for direction in [1i16, -1i16] {
+                let mut octave = if direction == 1 { center } else { center - 1 };
+                while window.octaves.contains(&(octave as i8)) {
+                    if new_bass + 12 * octave < 0 || new_top + 12 * octave > 127 {
+                        break;
+                    }
+                    let bass_gap = (bass - new_bass - 12 * octave).unsigned_abs();
+                    let top_gap = (top - new_top - 12 * octave).unsigned_abs();
+                    if let Some((best_sv, _, _, _)) = &best {
+                        if bass_gap > *best_sv {
+                            break;
+                        }
+                        if top_gap > *best_sv {
+                            octave += direction;
+                            continue;
+                        }
+                    }
+                    let shifted = new_chord.apply_inversion(octave as i8, inversion)?;
+                    if let Ok(p) = self.diff(&shifted) {
+                        if best.as_ref().is_none_or(|(best_sv, best_octave, best_inversion, _)| (p.sv, octave, inversion) < (*best_sv, *best_octave, *best_inversion)) {
+                            best = Some((p.sv, octave, inversion, shifted));
+                        }
+                    }
+                    octave += direction;
+                }
             }
-        }) {
-            Some(selected_inversion_map) => self.find_best_chord_pairs(&new_chord.apply_inversion(selected_inversion_map.0, selected_inversion_map.1).unwrap()),
+        }
+        match best {
+            Some((_, _, _, selected)) => self.find_best_chord_pairs(&selected),
             None => Err(ParseCNChordError { msg: String::from("Unknown Error") })
         }
     }
 
-    /// traverse all inversions
+    /// traverse all assignments of the pitch classes of `new_chord` to the voices of this chord
+    ///
+    /// Every pitch class of `new_chord` has to be sounded; a voice may double a pitch class
+    /// or split into two when `new_chord` has more pitch classes than this chord has voices.
     #[allow(dead_code)]
     pub fn find_vec_by_pitch_class(&self, new_chord: &CNChord) -> Result<(CNChord, CNChord), ParseCNChordError> {
-        // convert to pitch classes
-        let pitch_classes = new_chord.get_pitch_classes();
-        let mut unused_pitch_classes = pitch_classes.to_vec();
-        let mut new_pitches = vec! {};
-        for pitch in &self._pitches {
-            let new_pitch = pitch.get_nearest_pitch_by_pitch_class(&unused_pitch_classes);
-            new_pitches.push(new_pitch);
-            let pos = unused_pitch_classes.iter().position(|unused_pitch_class| *unused_pitch_class == new_pitch.get_pitch_class()).unwrap();
-            unused_pitch_classes.remove(pos);
+        let mapping = self.find_pitch_class_mapping(&new_chord.get_pitch_classes(), &PitchClassMatchOptions::default())?;
+        Ok((
+            CNChord::from_notes(&mapping.iter().map(|(voice, _)| self._pitches[*voice]).collect::<Vec<Pitch>>(), false),
+            CNChord::from_notes(&mapping.iter().map(|(_, pitch)| *pitch).collect::<Vec<Pitch>>(), false),
+        ))
+    }
+
+    /// Assign `pitch_classes` to the voices of this chord with the minimal total movement.
+    ///
+    /// Each voice moves to the nearest pitch of its pitch class (downwards on a tritone).
+    /// Returns `(voice index, new pitch)` pairs ordered by voice; a voice appears twice when it splits.
+    pub fn find_pitch_class_mapping(&self, pitch_classes: &[PitchClass], options: &PitchClassMatchOptions) -> Result<Vec<(usize, Pitch)>, ParseCNChordError> {
+        let pitch_classes: Vec<PitchClass> = pitch_classes.iter().unique_by(|pitch_class| pitch_class.0).cloned().collect();
+        let (n, k) = (self.t_size(), pitch_classes.len());
+        if n == 0 || k == 0 {
+            return Err(ParseCNChordError { msg: String::from("Cannot match an empty chord") });
+        }
+        let max_splits = options.max_splits.min(n);
+        let full_mask = (1usize << k) - 1;
+        // nearest[voice][pitch class] = (new pitch, distance)
+        let nearest: Vec<Vec<(Pitch, u32)>> = self._pitches.iter().map(|pitch| {
+            pitch_classes.iter().map(|pitch_class| {
+                let new_pitch = pitch.get_nearest_pitch_by_pitch_class(&vec![pitch_class.clone()]);
+                (new_pitch, u32::from(new_pitch.0.abs_diff(pitch.0)))
+            }).collect()
+        }).collect();
+        // choices of a single voice: one pitch class, or two distinct ones when splitting
+        let choices: Vec<Vec<usize>> = (0..k).map(|c| vec![c])
+            .chain((0..k).tuple_combinations().map(|(c1, c2)| vec![c1, c2]))
+            .collect();
+        let index = |mask: usize, splits: usize| mask * (max_splits + 1) + splits;
+        let states = (full_mask + 1) * (max_splits + 1);
+        let mut cost = vec![u32::MAX; states];
+        cost[index(0, 0)] = 0;
+        // choice[voice][state after the voice] = (state before the voice, choice index)
+        let mut choice: Vec<Vec<Option<(usize, usize)>>> = vec![vec![None; states]; n];
+        for voice in 0..n {
+            let mut next = vec![u32::MAX; states];
+            for (mask, splits) in itertools::iproduct!(0..=full_mask, 0..=max_splits) {
+                let current = cost[index(mask, splits)];
+                if current == u32::MAX {
+                    continue;
+                }
+                for (c, chosen) in choices.iter().enumerate() {
+                    let next_splits = splits + chosen.len() - 1;
+                    if next_splits > max_splits {
+                        continue;
+                    }
+                    let next_mask = chosen.iter().fold(mask, |m, pitch_class| m | (1 << pitch_class));
+                    let candidate = current + chosen.iter().map(|pitch_class| nearest[voice][*pitch_class].1).sum::<u32>();
+                    if candidate < next[index(next_mask, next_splits)] {
+                        next[index(next_mask, next_splits)] = candidate;
+                        choice[voice][index(next_mask, next_splits)] = Some((index(mask, splits), c));
+                    }
+                }
+            }
+            cost = next;
+        }
+        let selected = itertools::iproduct!(0..=full_mask, 0..=max_splits)
+            .filter(|(mask, splits)| {
+                let sounded = mask.count_ones() as usize;
+                cost[index(*mask, *splits)] != u32::MAX
+                    && k - sounded <= options.max_omissions
+                    && n + splits - sounded <= options.max_doublings
+            })
+            .min_by_key(|(mask, splits)| (cost[index(*mask, *splits)], *splits));
+        match selected {
+            Some((mask, splits)) => {
+                let mut mapping = vec![];
+                let mut state = index(mask, splits);
+                for voice in (0..n).rev() {
+                    let (previous_state, c) = choice[voice][state].unwrap();
+                    for pitch_class in choices[c].iter().rev() {
+                        mapping.push((voice, nearest[voice][*pitch_class].0));
+                    }
+                    state = previous_state;
+                }
+                mapping.reverse();
+                Ok(mapping)
+            }
+            None => Err(ParseCNChordError {
+                msg: format!("Cannot match {} voices to {} pitch classes", n, k)
+            })
         }
-        let new_chord = CNChord::from_notes(&new_pitches, false);
-        println!("{}", new_chord);
-        Ok(((*self).clone(), new_chord))
     }
 
     /// interface of '_find_vec'
@@ -228,20 +438,41 @@ impl CNChord {
     /// In original C++ Implementation
     #[allow(dead_code)]
     pub fn find_vec(&self, new_chord: &CNChord, _in_analyser: bool, in_substitution: bool) -> Result<(CNChord, CNChord), ParseCNChordError> {
-        match in_substitution {
-            false => self.find_best_chord_pairs(new_chord),
-            true => self.find_vec_simple(new_chord),
-            // true => self.find_vec_by_pitch_class(new_chord)
+        self.find_vec_with(new_chord, in_substitution, VoiceMatcher::default())
+    }
+
+    /// `find_vec`, pairing the voices with `matcher` outside of substitution
+    pub fn find_vec_with(&self, new_chord: &CNChord, in_substitution: bool, matcher: VoiceMatcher) -> Result<(CNChord, CNChord), ParseCNChordError> {
+        match (in_substitution, matcher) {
+            (false, VoiceMatcher::ChordPairs) => self.find_best_chord_pairs(new_chord),
+            (false, VoiceMatcher::VoiceMapping(max_split_merge)) => self.find_best_voice_mapping(new_chord, max_split_merge),
+            (true, _) => self.find_vec_simple(new_chord),
+            // (true, _) => self.find_vec_by_pitch_class(new_chord)
         }
     }
 
+    #[allow(dead_code)]
     pub fn get_pitch_classes(&self) -> Vec<PitchClass> {
         self._pitches.iter().map(|pitch| pitch.get_pitch_class()).dedup().collect::<Vec<PitchClass>>()
     }
 
+    /// every pitch class of the chord once, in the order of its first note
+    pub fn get_distinct_pitch_classes(&self) -> Vec<PitchClass> {
+        self._pitches.iter().map(|pitch| pitch.get_pitch_class()).unique_by(|pitch_class| pitch_class.0).collect::<Vec<PitchClass>>()
+    }
+
+    pub fn get_pitch_class_set(&self) -> PitchClassSet {
+        PitchClassSet::from_pitch_classes(&self.get_distinct_pitch_classes())
+    }
+
+    /// c; number of distinct pitches both chords contain
+    pub fn common_notes(&self, chord: &CNChord) -> usize {
+        self._pitches.iter().unique_by(|pitch| pitch.0).filter(|pitch| chord._pitches.contains(pitch)).count()
+    }
+
     /// n; size of notes
     pub fn t_size(&self) -> usize {
-        return self._pitches.len();
+        self._pitches.len()
     }
 
     pub fn apply_inversion(&self, octive: i8, inversion: usize) -> Result<CNChord, ParseCNChordError> {
@@ -249,13 +480,13 @@ impl CNChord {
         // handling octive
         let octive_to_shift_due_to_inversion = match inversion {
             0 => 0,
-            _ => (self._pitches[self._pitches.len() - 1].0 - self._pitches[inversion - 1].0 + 12 - 1) / 12
+            _ => (self._pitches[self._pitches.len() - 1].0 - self._pitches[inversion - 1].0).div_ceil(12)
         };
-        let new_pitches = self._pitches[inversion..self._pitches.len()].iter().cloned().chain(self._pitches[0..inversion].iter().map(|i| *i + i8::try_from(12 * octive_to_shift_due_to_inversion).unwrap())).map(|item| item + i8::try_from(12 * octive).unwrap()).collect();
-        return Ok(CNChord::from_notes(&new_pitches, false));
+        let new_pitches: Vec<Pitch> = self._pitches[inversion..self._pitches.len()].iter().cloned().chain(self._pitches[0..inversion].iter().map(|i| *i + i8::try_from(12 * octive_to_shift_due_to_inversion).unwrap())).map(|item| item + 12 * octive).collect();
+        Ok(CNChord::from_notes(&new_pitches, false))
     }
 
-    pub fn apply_expansion(&self, expansion_map: &Vec<&usize>, total_size: usize) -> CNChord {
+    pub fn apply_expansion(&self, expansion_map: &[&usize], total_size: usize) -> CNChord {
         // FIXME: return Result instead
         let mut ret = vec! {};
         let mut counter: usize = 0;
@@ -265,14 +496,13 @@ impl CNChord {
                 counter += 1;
             }
         }
-        let c = CNChord::from_notes(&ret, false);
-        return c;
+        CNChord::from_notes(&ret, false)
     }
 
     /// traverse all combinations
     pub fn find_best_chord_pairs(&self, chord: &CNChord) -> Result<(CNChord, CNChord), ParseCNChordError> {
         if self.t_size() == chord.t_size() {
-            return Ok(((*self).clone(), (*chord).clone()));
+            Ok(((*self).clone(), (*chord).clone()))
         } else if self.t_size() > chord.t_size() {
             match chord.find_best_chord_pairs(self) {
                 Ok((f, s)) => Ok((s, f)),
@@ -293,17 +523,83 @@ impl CNChord {
                 Some(p) => {
                     let expanded_chord = self.apply_expansion(p, chord.t_size());
                     assert_eq!(expanded_chord.t_size(), chord.t_size());
-                    return Ok((expanded_chord, (*chord).clone()));
+                    Ok((expanded_chord, (*chord).clone()))
                 }
                 None => Err(ParseCNChordError { msg: String::from("Unknown Error") })
             }
         }
     }
 
+    /// traverse all order-preserving many-to-many voice mappings
+    ///
+    /// Unlike `find_best_chord_pairs`, which can only duplicate voices of the smaller chord,
+    /// a voice may split into several voices of the other chord while other voices merge,
+    /// in the same transition. `max_split_merge` caps the number of splits plus merges;
+    /// it has to be at least the difference of the two chord sizes.
+    pub fn find_best_voice_mapping(&self, chord: &CNChord, max_split_merge: usize) -> Result<(CNChord, CNChord), ParseCNChordError> {
+        let (n, m) = (self.t_size(), chord.t_size());
+        if n == 0 || m == 0 {
+            return Err(ParseCNChordError { msg: String::from("Cannot map voices of an empty chord") });
+        }
+        if max_split_merge < n.abs_diff(m) {
+            return Err(ParseCNChordError {
+                msg: format!("{} splits / merges cannot map {} voices to {} voices", max_split_merge, n, m)
+            });
+        }
+        // A path from (0, 0) to (n - 1, m - 1) takes at most n + m - 2 steps.
+        let k_max = max_split_merge.min(n + m - 2);
+        let index = |i: usize, j: usize, k: usize| (i * m + j) * (k_max + 1) + k;
+        let mut cost = vec![u32::MAX; n * m * (k_max + 1)];
+        let mut from: Vec<Option<(usize, usize, usize)>> = vec![None; n * m * (k_max + 1)];
+        let distance = |i: usize, j: usize| u32::from(self._pitches[i].0.abs_diff(chord._pitches[j].0));
+        cost[index(0, 0, 0)] = distance(0, 0);
+        for (i, j) in itertools::iproduct!(0..n, 0..m) {
+            for k in 0..=k_max {
+                let current = cost[index(i, j, k)];
+                if current == u32::MAX {
+                    continue;
+                }
+                // (next i, next j, next k): move both voices on, merge into voice j, or split voice i
+                let steps = [
+                    (i + 1, j + 1, k),
+                    (i + 1, j, k + 1),
+                    (i, j + 1, k + 1),
+                ];
+                for (ni, nj, nk) in steps {
+                    if ni >= n || nj >= m || nk > k_max {
+                        continue;
+                    }
+                    let candidate = current + distance(ni, nj);
+                    if candidate < cost[index(ni, nj, nk)] {
+                        cost[index(ni, nj, nk)] = candidate;
+                        from[index(ni, nj, nk)] = Some((i, j, k));
+                    }
+                }
+            }
+        }
+        // Prefer the fewest splits and merges among the cheapest mappings
+        match (0..=k_max).filter(|k| cost[index(n - 1, m - 1, *k)] != u32::MAX).min_by_key(|k| cost[index(n - 1, m - 1, *k)]) {
+            Some(k) => {
+                let mut pairs = vec![(n - 1, m - 1)];
+                let mut cursor = from[index(n - 1, m - 1, k)];
+                while let Some((i, j, k)) = cursor {
+                    pairs.push((i, j));
+                    cursor = from[index(i, j, k)];
+                }
+                pairs.reverse();
+                Ok((
+                    CNChord::from_notes(&pairs.iter().map(|(i, _)| self._pitches[*i]).collect::<Vec<Pitch>>(), false),
+                    CNChord::from_notes(&pairs.iter().map(|(_, j)| chord._pitches[*j]).collect::<Vec<Pitch>>(), false),
+                ))
+            }
+            None => Err(ParseCNChordError { msg: String::from("Unknown Error") })
+        }
+    }
+
     pub fn diff(&self, chord: &CNChord) -> Result<ChordDiff, ParseCNChordError> {
         if self.t_size() == chord.t_size() {
-            /// Return the diff of two chord with the same size pitch by pitch.
-            /// It does not return a score - upper function can still choose what to evaluate based on need
+            // Return the diff of two chord with the same size pitch by pitch.
+            // It does not return a score - upper function can still choose what to evaluate based on need
             Ok(ChordDiff::new(
                 (0..(self.t_size().min(chord.t_size()))).map(|index| i16::from(chord._pitches[index].0) - i16::from(self._pitches[index].0)).collect()
             ))
@@ -346,6 +642,18 @@ pub struct ParseCNChordError {
     msg: String,
 }
 
+impl ParseCNChordError {
+    pub fn new(msg: String) -> Self {
+        ParseCNChordError { msg }
+    }
+}
+
+impl fmt::Display for ParseCNChordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<ParseCNChordError: {}>", self.msg)
+    }
+}
+
 impl FromStr for CNChord {
     type Err = ParseCNChordError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -364,15 +672,15 @@ impl FromStr for CNChord {
                             }
                         }
                         rule => Err(ParseCNChordError {
-                            msg: String::from(format!("Unknown rule {:?}", rule))
+                            msg: format!("Unknown rule {:?}", rule)
                         })
                     },
                     None => Err(ParseCNChordError {
-                        msg: String::from(format!("{:?}", pairs))
+                        msg: format!("{:?}", pairs)
                     })
                 }
             }
-            Err(e) => Err(ParseCNChordError { msg: String::from(e.to_string()) })
+            Err(e) => Err(ParseCNChordError { msg: e.to_string() })
         }
     }
 }
@@ -409,6 +717,38 @@ mod tests {
         assert_eq!(result_tuple.1.to_string(), "C4, E4, G4, B-4");
     }
 
+    #[test]
+    fn find_best_voice_mapping_1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let c_dominant_7: CNChord = CNChord::from_str("C4 E4 G4 B-4").unwrap();
+        let result_tuple = c_major.find_best_voice_mapping(&c_dominant_7, 1).unwrap();
+        assert_eq!(result_tuple.0.to_string(), "C4, E4, G4, G4");
+        assert_eq!(result_tuple.1.to_string(), "C4, E4, G4, B-4");
+    }
+
+    #[test]
+    fn find_best_voice_mapping_2() {
+        // C4 splits into B3 / C4 while D5 and E5 merge into E5
+        let source: CNChord = CNChord::from_str("C4 G4 D5 E5").unwrap();
+        let target: CNChord = CNChord::from_str("B3 C4 G4 E5").unwrap();
+        let one_to_one = source.find_best_voice_mapping(&target, 1).unwrap();
+        assert_eq!(one_to_one.0.diff(&one_to_one.1).unwrap().sv, 15);
+        let result_tuple = source.find_best_voice_mapping(&target, 2).unwrap();
+        assert_eq!(result_tuple.0.to_string(), "C4, C4, G4, D5, E5");
+        assert_eq!(result_tuple.1.to_string(), "B3, C4, G4, E5, E5");
+        assert_eq!(result_tuple.0.diff(&result_tuple.1).unwrap().sv, 3);
+    }
+
+    #[test]
+    fn find_best_voice_mapping_3() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let c_major_ninth: CNChord = CNChord::from_str("C4 E4 G4 B4 D5").unwrap();
+        assert!(c_major.find_best_voice_mapping(&c_major_ninth, 1).is_err());
+        let result_tuple = c_major.find_best_voice_mapping(&c_major_ninth, 2).unwrap();
+        assert_eq!(result_tuple.0.to_string(), "C4, E4, G4, G4, G4");
+        assert_eq!(result_tuple.1.to_string(), "C4, E4, G4, B4, D5");
+    }
+
     #[test]
     fn find_vec1_1() {
         let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
@@ -427,6 +767,22 @@ mod tests {
         assert_eq!(result_tuple.1.to_string(), "C4, F4, A4");
     }
 
+    #[test]
+    fn find_vec1_3() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let f_major: CNChord = CNChord::from_str("F1 A1 C2").unwrap();
+        // the default window only reaches one octave down
+        let result_tuple = c_major.find_vec_simple(&f_major).unwrap();
+        assert_eq!(result_tuple.1.to_string(), "C2, F2, A2");
+        let wide_window = InversionSearchWindow { octaves: -4..=4, max_inversion: None };
+        let result_tuple = c_major.find_vec_in_window(&f_major, &wide_window).unwrap();
+        assert_eq!(result_tuple.0.to_string(), "C4, E4, G4");
+        assert_eq!(result_tuple.1.to_string(), "C4, F4, A4");
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty_window = InversionSearchWindow { octaves: 1..=0, max_inversion: None };
+        assert!(c_major.find_vec_in_window(&f_major, &empty_window).is_err());
+    }
+
     #[test]
     fn find_vec2_1() {
         let b_diminished: CNChord = CNChord::from_str("B3 D4 F4").unwrap();
@@ -454,6 +810,32 @@ mod tests {
         assert_eq!(result_tuple.1.to_string(), "C3, G3, E4, B4");
     }
 
+    #[test]
+    fn find_vec3_3() {
+        let c_major: CNChord = CNChord::from_str("C3 G3 E4 C5").unwrap();
+        let d_minor_seventh: CNChord = CNChord::from_str("D3 F3 A3 C4").unwrap();
+        let pitch_classes = d_minor_seventh.get_pitch_classes();
+        let mapping = c_major.find_pitch_class_mapping(&pitch_classes, &PitchClassMatchOptions::default()).unwrap();
+        assert_eq!(mapping.iter().map(|(voice, pitch)| c_major._pitches[*voice].0.abs_diff(pitch.0)).sum::<u8>(), 5);
+        let options = PitchClassMatchOptions { max_omissions: 1, max_doublings: 1, max_splits: 0 };
+        let mapping = c_major.find_pitch_class_mapping(&pitch_classes, &options).unwrap();
+        assert_eq!(iterable_to_str(mapping.iter().map(|(voice, pitch)| format!("{}:{}", voice, pitch))), "[0:C3, 1:A3, 2:F4, 3:C5]");
+        let options = PitchClassMatchOptions { max_omissions: 0, max_doublings: 0, max_splits: 0 };
+        let c_major_ninth: CNChord = CNChord::from_str("C3 E3 G3 B3 D4").unwrap();
+        assert!(c_major.find_pitch_class_mapping(&c_major_ninth.get_pitch_classes(), &options).is_err());
+    }
+
+    #[test]
+    fn find_vec3_4() {
+        // the nearest pitches are looked for inside the MIDI range only
+        let lowest = CNChord::from_notes(&[Pitch(0), Pitch(4), Pitch(7)], false);
+        let mapping = lowest.find_pitch_class_mapping(&[PitchClass(11), PitchClass(6)], &PitchClassMatchOptions::default()).unwrap();
+        assert_eq!(mapping.iter().map(|(voice, pitch)| (*voice, pitch.0)).collect::<Vec<(usize, u8)>>(), vec![(0, 6), (1, 6), (2, 11)]);
+        let highest = CNChord::from_notes(&[Pitch(127)], false);
+        let mapping = highest.find_pitch_class_mapping(&[PitchClass(1), PitchClass(8)], &PitchClassMatchOptions::default()).unwrap();
+        assert_eq!(mapping.iter().map(|(_, pitch)| pitch.0).sorted().collect::<Vec<u8>>(), vec![116, 121]);
+    }
+
     #[test]
     fn find_vec4_1() {
         let c_major: CNChord = CNChord::from_str("C3 E3 G3").unwrap();
@@ -471,4 +853,26 @@ mod tests {
         assert_eq!(result_tuple.0.to_string(), "C3, C3, E3, G3");
         assert_eq!(result_tuple.1.to_string(), "B2, C3, E3, G3");
     }
+
+    #[test]
+    fn find_vec5_1() {
+        // three voices thicken to five: C4 splits downwards while D5 and E5 merge into E5
+        let source: CNChord = CNChord::from_str("C4 D5 E5").unwrap();
+        let target: CNChord = CNChord::from_str("B3 C4 D4 E4 E5").unwrap();
+        let result_tuple = source.find_vec(&target, false, false).unwrap();
+        assert_eq!(result_tuple.0.to_string(), "C4, C4, C4, D5, E5");
+        assert_eq!(result_tuple.0.diff(&result_tuple.1).unwrap().sv, 13);
+        let result_tuple = source.find_vec_with(&target, false, VoiceMatcher::VoiceMapping(4)).unwrap();
+        assert_eq!(result_tuple.0.to_string(), "C4, C4, C4, C4, D5, E5");
+        assert_eq!(result_tuple.1.to_string(), "B3, C4, D4, E4, E5, E5");
+        assert_eq!(result_tuple.0.diff(&result_tuple.1).unwrap().sv, 9);
+        assert!(source.find_vec_with(&target, false, VoiceMatcher::VoiceMapping(1)).is_err());
+    }
+
+    #[test]
+    fn pitch_classes1() {
+        let c_major: CNChord = CNChord::from_str("C3 E3 G3 C4 E4").unwrap();
+        assert_eq!(c_major.get_pitch_classes().iter().map(|pitch_class| pitch_class.0).collect::<Vec<u8>>(), vec![0, 4, 7, 0, 4]);
+        assert_eq!(c_major.get_distinct_pitch_classes().iter().map(|pitch_class| pitch_class.0).collect::<Vec<u8>>(), vec![0, 4, 7]);
+    }
 }
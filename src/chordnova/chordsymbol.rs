@@ -0,0 +1,191 @@
+use crate::chordnova::pitchparser::pest::Parser;
+use crate::chordnova::pitchparser::pest::iterators::Pair;
+use crate::chordnova::pitchparser::Rule;
+use crate::chordnova::pitchparser::PitchParser;
+
+use std::fmt;
+use std::str::FromStr;
+use crate::chordnova::pcset::PitchClassSet;
+use crate::chordnova::pitch::{Accidental, Pitch, PitchClass, Stepname};
+
+/// Chord types a chord symbol can name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChordType {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Suspended2,
+    Suspended4,
+    Major6,
+    Minor6,
+    Dominant7,
+    Major7,
+    Minor7,
+    MinorMajor7,
+    HalfDiminished7,
+    Diminished7,
+    Dominant7Suspended4,
+    Dominant9,
+    Major9,
+    Minor9,
+}
+
+impl ChordType {
+    /// intervals above the root
+    pub fn intervals(&self) -> &'static [u8] {
+        match self {
+            ChordType::Major => &[0, 4, 7],
+            ChordType::Minor => &[0, 3, 7],
+            ChordType::Diminished => &[0, 3, 6],
+            ChordType::Augmented => &[0, 4, 8],
+            ChordType::Suspended2 => &[0, 2, 7],
+            ChordType::Suspended4 => &[0, 5, 7],
+            ChordType::Major6 => &[0, 4, 7, 9],
+            ChordType::Minor6 => &[0, 3, 7, 9],
+            ChordType::Dominant7 => &[0, 4, 7, 10],
+            ChordType::Major7 => &[0, 4, 7, 11],
+            ChordType::Minor7 => &[0, 3, 7, 10],
+            ChordType::MinorMajor7 => &[0, 3, 7, 11],
+            ChordType::HalfDiminished7 => &[0, 3, 6, 10],
+            ChordType::Diminished7 => &[0, 3, 6, 9],
+            ChordType::Dominant7Suspended4 => &[0, 5, 7, 10],
+            ChordType::Dominant9 => &[0, 2, 4, 7, 10],
+            ChordType::Major9 => &[0, 2, 4, 7, 11],
+            ChordType::Minor9 => &[0, 2, 3, 7, 10],
+        }
+    }
+
+    /// suffix written after the root
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            ChordType::Major => "",
+            ChordType::Minor => "m",
+            ChordType::Diminished => "dim",
+            ChordType::Augmented => "aug",
+            ChordType::Suspended2 => "sus2",
+            ChordType::Suspended4 => "sus4",
+            ChordType::Major6 => "6",
+            ChordType::Minor6 => "m6",
+            ChordType::Dominant7 => "7",
+            ChordType::Major7 => "maj7",
+            ChordType::Minor7 => "m7",
+            ChordType::MinorMajor7 => "mMaj7",
+            ChordType::HalfDiminished7 => "m7b5",
+            ChordType::Diminished7 => "dim7",
+            ChordType::Dominant7Suspended4 => "7sus4",
+            ChordType::Dominant9 => "9",
+            ChordType::Major9 => "maj9",
+            ChordType::Minor9 => "m9",
+        }
+    }
+}
+
+impl FromStr for ChordType {
+    type Err = ParseChordSymbolError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            ChordType::Major, ChordType::Minor, ChordType::Diminished, ChordType::Augmented,
+            ChordType::Suspended2, ChordType::Suspended4, ChordType::Major6, ChordType::Minor6,
+            ChordType::Dominant7, ChordType::Major7, ChordType::Minor7, ChordType::MinorMajor7,
+            ChordType::HalfDiminished7, ChordType::Diminished7, ChordType::Dominant7Suspended4,
+            ChordType::Dominant9, ChordType::Major9, ChordType::Minor9,
+        ].into_iter()
+            .find(|chord_type| chord_type.suffix() == s)
+            .ok_or(ParseChordSymbolError { msg: format!("Unknown chord type {}", s) })
+    }
+}
+
+/// A lead-sheet chord symbol, optionally over a bass note
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChordSymbol {
+    pub root: PitchClass,
+    pub chord_type: ChordType,
+    pub bass: Option<PitchClass>,
+}
+
+impl ChordSymbol {
+    /// pitch classes of the chord, including a bass note outside of it
+    pub fn pitch_class_set(&self) -> PitchClassSet {
+        let mut pitch_classes = self.chord_type.intervals().iter().map(|interval| (self.root.0 + interval) % 12).collect::<Vec<u8>>();
+        if let Some(bass) = &self.bass {
+            pitch_classes.push(bass.0);
+        }
+        PitchClassSet::from_integers(&pitch_classes)
+    }
+}
+
+impl fmt::Display for ChordSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (stepname, accidental) = self.root.to_step_name();
+        write!(f, "{}{}{}", stepname, accidental, self.chord_type.suffix())?;
+        if let Some(bass) = &self.bass {
+            let (stepname, accidental) = bass.to_step_name();
+            write!(f, "/{}{}", stepname, accidental)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseChordSymbolError {
+    msg: String,
+}
+
+impl fmt::Display for ParseChordSymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<ParseChordSymbolError: {}>", self.msg)
+    }
+}
+
+/// pitch class of a ROOT pair, e.g. "B-"
+fn parse_root(pair: Pair<Rule>) -> PitchClass {
+    let mut inner = pair.into_inner();
+    let stepname: Stepname = inner.next().unwrap().as_str().parse().unwrap();
+    let accidental: Accidental = inner.next().unwrap().as_str().parse().unwrap();
+    Pitch::from_stepname(stepname, accidental, Some(4)).get_pitch_class()
+}
+
+impl FromStr for ChordSymbol {
+    type Err = ParseChordSymbolError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pairs = PitchParser::parse(Rule::FULL_CHORD_SYMBOL, s)
+            .map_err(|e| ParseChordSymbolError { msg: e.to_string() })?;
+        let mut inner = match pairs.next() {
+            Some(pair) if pair.as_rule() == Rule::CHORD_SYMBOL => pair.into_inner(),
+            _ => return Err(ParseChordSymbolError { msg: format!("Not a chord symbol: {}", s) })
+        };
+        let root = parse_root(inner.next().unwrap());
+        let chord_type = inner.next().unwrap().as_str().parse()?;
+        let bass = inner.next().map(parse_root);
+        Ok(ChordSymbol { root, chord_type, bass })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse1() {
+        let symbol = ChordSymbol::from_str("F#m7").unwrap();
+        assert_eq!(symbol.root, PitchClass(6));
+        assert_eq!(symbol.chord_type, ChordType::Minor7);
+        assert_eq!(symbol.bass, None);
+        assert_eq!(symbol.pitch_class_set().pitch_classes(), vec![1, 4, 6, 9]);
+        let symbol = ChordSymbol::from_str("B-maj7/D").unwrap();
+        assert_eq!(symbol.chord_type, ChordType::Major7);
+        assert_eq!(symbol.bass, Some(PitchClass(2)));
+        assert_eq!(symbol.to_string(), "B-maj7/D");
+        assert_eq!(ChordSymbol::from_str("C/B-").unwrap().pitch_class_set().pitch_classes(), vec![0, 4, 7, 10]);
+    }
+
+    #[test]
+    fn parse2() {
+        assert!(ChordSymbol::from_str("H7").is_err());
+        assert!(ChordSymbol::from_str("Cmaj").is_err());
+        assert!(ChordSymbol::from_str("C7/").is_err());
+        assert_eq!(ChordSymbol::from_str("Edim7").unwrap().to_string(), "Edim7");
+        assert_eq!(ChordSymbol::from_str("C-").unwrap().root, PitchClass(11));
+    }
+}
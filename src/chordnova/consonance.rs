@@ -0,0 +1,158 @@
+use itertools::Itertools;
+use crate::chordnova::chord::CNChord;
+
+/// Consonance measures of a whole chord; the smaller, the more consonant
+#[allow(dead_code)]
+pub enum ConsonanceMeasure {
+    /// Erlich's harmonic entropy, averaged over every pair of notes
+    HarmonicEntropy(HarmonicEntropy),
+    /// Euler's gradus suavitatis of the chord tuned to the nearest just ratios above the bass
+    EulerGradus,
+    /// relative periodicity: period of the chord tuned as for `EulerGradus`, in periods of the bass
+    Periodicity,
+}
+
+impl ConsonanceMeasure {
+    pub fn consonance(&self, chord: &CNChord) -> f64 {
+        match self {
+            ConsonanceMeasure::HarmonicEntropy(model) => model.entropy(chord),
+            ConsonanceMeasure::EulerGradus => gradus(chord) as f64,
+            ConsonanceMeasure::Periodicity => periodicity(chord) as f64,
+        }
+    }
+}
+
+/// Harmonic entropy with a Gaussian spread over all ratios n/d with n * d up to `max_complexity`,
+/// each ratio weighted by 1 / sqrt(n * d)
+pub struct HarmonicEntropy {
+    /// entropy of every interval from 0 to 127 semitones
+    table: Vec<f64>,
+}
+
+impl HarmonicEntropy {
+    /// `spread` is the standard deviation of the Gaussian, in cents
+    pub fn new(spread: f64, max_complexity: u32) -> Self {
+        let ratios = (1..=max_complexity)
+            .flat_map(|d| (d..=max_complexity / d).map(move |n| (n, d)))
+            .filter(|(n, d)| gcd(u128::from(*n), u128::from(*d)) == 1)
+            .map(|(n, d)| (1200.0 * (f64::from(n) / f64::from(d)).log2(), 1.0 / f64::from(n * d).sqrt()))
+            .collect::<Vec<(f64, f64)>>();
+        let table = (0..128).map(|semitones| {
+            let cents = f64::from(semitones) * 100.0;
+            let weights = ratios.iter()
+                .map(|(ratio_cents, weight)| weight * (-(cents - ratio_cents).powi(2) / (2.0 * spread * spread)).exp())
+                .collect::<Vec<f64>>();
+            let total = weights.iter().sum::<f64>();
+            weights.iter()
+                .filter(|weight| **weight > 0.0)
+                .map(|weight| -(weight / total) * (weight / total).ln())
+                .sum()
+        }).collect();
+        HarmonicEntropy { table }
+    }
+
+    /// mean entropy of the intervals between every pair of notes
+    pub fn entropy(&self, chord: &CNChord) -> f64 {
+        let entropies = chord._pitches.iter().tuple_combinations()
+            .map(|(a, b)| self.table[usize::from(a.0.abs_diff(b.0))])
+            .collect::<Vec<f64>>();
+        match entropies.len() {
+            0 => 0.0,
+            count => entropies.iter().sum::<f64>() / count as f64
+        }
+    }
+}
+
+impl Default for HarmonicEntropy {
+    fn default() -> Self {
+        HarmonicEntropy::new(17.0, 10000)
+    }
+}
+
+/// Nearest just ratio of every interval within the octave
+const JUST_RATIOS: [(u128, u128); 12] = [
+    (1, 1), (16, 15), (9, 8), (6, 5), (5, 4), (4, 3), (45, 32), (3, 2), (8, 5), (5, 3), (16, 9), (15, 8),
+];
+
+fn gcd(a: u128, b: u128) -> u128 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b)
+    }
+}
+
+fn lcm(a: u128, b: u128) -> u128 {
+    a / gcd(a, b) * b
+}
+
+/// just ratio of every note to the bass, as (numerator, denominator) in lowest terms
+fn just_ratios(chord: &CNChord) -> Vec<(u128, u128)> {
+    let bass = match chord._pitches.iter().min() {
+        Some(bass) => bass.0,
+        None => return vec![]
+    };
+    chord._pitches.iter().map(|pitch| {
+        let interval = pitch.0 - bass;
+        let (n, d) = JUST_RATIOS[usize::from(interval % 12)];
+        let n = n << (interval / 12);
+        (n / gcd(n, d), d / gcd(n, d))
+    }).collect()
+}
+
+/// Γ(n) = 1 + Σ e(p - 1) for n = Π p^e
+fn gradus_of(mut n: u128) -> u32 {
+    let mut gradus = 1;
+    let mut p = 2;
+    while n > 1 {
+        while n.is_multiple_of(p) {
+            gradus += (p - 1) as u32;
+            n /= p;
+        }
+        p += 1;
+    }
+    gradus
+}
+
+/// Γ of the least common multiple of the chord written as whole-number frequency ratios
+pub fn gradus(chord: &CNChord) -> u32 {
+    let ratios = just_ratios(chord);
+    let denominator = ratios.iter().fold(1, |l, (_, d)| lcm(l, *d));
+    let terms = ratios.iter().map(|(n, d)| n * denominator / d).collect::<Vec<u128>>();
+    let common = terms.iter().fold(0, |g, term| gcd(g, *term)).max(1);
+    gradus_of(terms.iter().fold(1, |l, term| lcm(l, term / common)))
+}
+
+/// least common multiple of the denominators of the ratios to the bass
+pub fn periodicity(chord: &CNChord) -> u128 {
+    just_ratios(chord).iter().fold(1, |l, (_, d)| lcm(l, *d))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn harmonic_entropy1() {
+        let model = HarmonicEntropy::new(17.0, 1000);
+        let entropies = ["C4 C5", "C4 G4", "C4 E4", "C4 F#4"].iter()
+            .map(|chord| model.entropy(&CNChord::from_str(chord).unwrap()))
+            .collect::<Vec<f64>>();
+        assert!(entropies.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", entropies);
+    }
+
+    #[test]
+    fn gradus1() {
+        assert_eq!(gradus(&CNChord::from_str("C4 E4 G4").unwrap()), 9);
+        assert_eq!(gradus(&CNChord::from_str("C4 E-4 G4").unwrap()), 9);
+        assert_eq!(gradus(&CNChord::from_str("C4 G4 C5").unwrap()), 5);
+        assert!(gradus(&CNChord::from_str("C4 D-4").unwrap()) > gradus(&CNChord::from_str("C4 E4 G4").unwrap()));
+    }
+
+    #[test]
+    fn periodicity1() {
+        assert_eq!(periodicity(&CNChord::from_str("C4 E4 G4").unwrap()), 4);
+        assert_eq!(periodicity(&CNChord::from_str("C4 E-4 G4").unwrap()), 10);
+        assert_eq!(periodicity(&CNChord::from_str("C3 G4").unwrap()), 1);
+    }
+}
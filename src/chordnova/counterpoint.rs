@@ -0,0 +1,221 @@
+use std::fmt;
+use itertools::Itertools;
+use crate::chordnova::chord::{CNChord, ParseCNChordError};
+use crate::chordnova::generator::{Candidate, CandidateFilter};
+use crate::chordnova::pitch::{Pitch, PitchClass};
+use crate::chordnova::voiceleading::VoiceLeading;
+
+/// Classical part-writing faults
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// two voices moving in the same direction from a perfect fifth to a perfect fifth
+    ParallelFifths,
+    /// two voices moving in the same direction from an octave (or unison) to an octave
+    ParallelOctaves,
+    /// outer voices reaching a perfect fifth in similar motion with a leap in the top voice
+    HiddenFifths,
+    /// outer voices reaching an octave in similar motion with a leap in the top voice
+    HiddenOctaves,
+    /// leading tone in an outer voice not rising to the tonic although the next chord has it
+    UnresolvedLeadingTone,
+    /// large leap not followed by a step in the opposite direction
+    UnrecoveredLeap,
+    /// neighbouring upper voices more than an octave apart
+    WideSpacing,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            Fault::ParallelFifths => "parallel fifths",
+            Fault::ParallelOctaves => "parallel octaves",
+            Fault::HiddenFifths => "hidden fifths",
+            Fault::HiddenOctaves => "hidden octaves",
+            Fault::UnresolvedLeadingTone => "unresolved leading tone",
+            Fault::UnrecoveredLeap => "unrecovered leap",
+            Fault::WideSpacing => "wide spacing",
+        })
+    }
+}
+
+/// A fault found in a progression
+#[derive(Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub fault: Fault,
+    /// index of the chord where the fault happens; motion faults point at the chord moved to
+    pub chord: usize,
+    /// voices involved, numbered from the bass of the chord moved from
+    pub voices: Vec<usize>,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at chord {} (voices {})", self.fault, self.chord, self.voices.iter().join(", "))
+    }
+}
+
+/// Scan matched voice leadings for the faults in `rules`
+pub struct CounterpointChecker {
+    pub rules: Vec<Fault>,
+    /// leaps wider than this many semitones have to be recovered
+    pub max_leap: u16,
+    /// leading tone of the key, if there is one
+    pub leading_tone: Option<PitchClass>,
+}
+
+impl Default for CounterpointChecker {
+    fn default() -> Self {
+        CounterpointChecker {
+            rules: vec![
+                Fault::ParallelFifths,
+                Fault::ParallelOctaves,
+                Fault::HiddenFifths,
+                Fault::HiddenOctaves,
+                Fault::UnresolvedLeadingTone,
+                Fault::UnrecoveredLeap,
+                Fault::WideSpacing,
+            ],
+            max_leap: 7,
+            leading_tone: None,
+        }
+    }
+}
+
+fn interval_class(lower: Pitch, upper: Pitch) -> u8 {
+    lower.0.abs_diff(upper.0) % 12
+}
+
+impl CounterpointChecker {
+    /// Check every chord of `progression` and every transition matched by `CNChord::find_vec`
+    #[allow(dead_code)]
+    pub fn check(&self, progression: &[CNChord]) -> Result<Vec<Finding>, ParseCNChordError> {
+        let voice_leadings = progression.iter().tuple_windows()
+            .map(|(from, to)| VoiceLeading::find(from, to, false))
+            .collect::<Result<Vec<VoiceLeading>, ParseCNChordError>>()?;
+        let mut findings = vec![];
+        for (index, chord) in progression.iter().enumerate() {
+            findings.extend(self.check_spacing(chord, index));
+        }
+        for (index, voice_leading) in voice_leadings.iter().enumerate() {
+            findings.extend(self.check_step(voice_leading, index + 1));
+        }
+        for (index, (first, second)) in voice_leadings.iter().tuple_windows().enumerate() {
+            findings.extend(self.check_leaps(first, second, index + 1));
+        }
+        Ok(findings)
+    }
+
+    /// faults of a single chord
+    pub fn check_spacing(&self, chord: &CNChord, index: usize) -> Vec<Finding> {
+        if !self.rules.contains(&Fault::WideSpacing) {
+            return vec![];
+        }
+        (1..chord.t_size()).tuple_windows()
+            .filter(|(lower, upper)| chord._pitches[*upper].0.abs_diff(chord._pitches[*lower].0) > 12)
+            .map(|(lower, upper)| Finding { fault: Fault::WideSpacing, chord: index, voices: vec![lower, upper] })
+            .collect()
+    }
+
+    /// faults of a single transition; `index` is the index of the chord moved to
+    pub fn check_step(&self, voice_leading: &VoiceLeading, index: usize) -> Vec<Finding> {
+        let (from, to, motion) = (&voice_leading.from, &voice_leading.to, &voice_leading.diff.diff_vec);
+        let size = from.len();
+        if size == 0 {
+            return vec![];
+        }
+        let mut findings = vec![];
+        for (lower, upper) in (0..size).tuple_combinations() {
+            if motion[lower] == 0 || motion[upper] == 0 || motion[lower].signum() != motion[upper].signum() {
+                continue;
+            }
+            let (before, after) = (interval_class(from[lower], from[upper]), interval_class(to[lower], to[upper]));
+            let fault = match (before, after) {
+                (7, 7) => Some(Fault::ParallelFifths),
+                (0, 0) => Some(Fault::ParallelOctaves),
+                (_, 7) if lower == 0 && upper == size - 1 && motion[upper].abs() > 2 => Some(Fault::HiddenFifths),
+                (_, 0) if lower == 0 && upper == size - 1 && motion[upper].abs() > 2 => Some(Fault::HiddenOctaves),
+                _ => None
+            };
+            if let Some(fault) = fault.filter(|fault| self.rules.contains(fault)) {
+                findings.push(Finding { fault, chord: index, voices: vec![lower, upper] });
+            }
+        }
+        if let Some(leading_tone) = self.leading_tone.as_ref().filter(|_| self.rules.contains(&Fault::UnresolvedLeadingTone)) {
+            let tonic = PitchClass((leading_tone.0 + 1) % 12);
+            if to.iter().any(|pitch| pitch.get_pitch_class() == tonic) {
+                for voice in [0, size - 1].into_iter().dedup() {
+                    if from[voice].get_pitch_class() == *leading_tone && motion[voice] != 1 {
+                        findings.push(Finding { fault: Fault::UnresolvedLeadingTone, chord: index, voices: vec![voice] });
+                    }
+                }
+            }
+        }
+        findings
+    }
+
+    /// leaps of `first` not recovered by `second`; `index` is the index of the chord between them
+    pub fn check_leaps(&self, first: &VoiceLeading, second: &VoiceLeading, index: usize) -> Vec<Finding> {
+        // voices can only be followed when the second transition starts where the first ends
+        if !self.rules.contains(&Fault::UnrecoveredLeap) || first.to != second.from {
+            return vec![];
+        }
+        let (leap, next) = (&first.diff.diff_vec, &second.diff.diff_vec);
+        (0..leap.len())
+            .filter(|voice| leap[*voice].unsigned_abs() > self.max_leap)
+            .filter(|voice| next[*voice].signum() != -leap[*voice].signum() || next[*voice].abs() > 2)
+            .map(|voice| Finding { fault: Fault::UnrecoveredLeap, chord: index, voices: vec![voice] })
+            .collect()
+    }
+}
+
+impl CandidateFilter for CounterpointChecker {
+    fn accept(&self, _prev: &CNChord, candidate: &Candidate) -> bool {
+        self.check_spacing(&candidate.chord, 1).is_empty() && self.check_step(&candidate.voice_leading, 1).is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn progression(chords: &[&str]) -> Vec<CNChord> {
+        chords.iter().map(|chord| CNChord::from_str(chord).unwrap()).collect()
+    }
+
+    #[test]
+    fn parallels1() {
+        let checker = CounterpointChecker::default();
+        let findings = checker.check(&progression(&["C3 G3 E4 C5", "D3 A3 F4 D5"])).unwrap();
+        assert_eq!(findings, vec![
+            Finding { fault: Fault::ParallelFifths, chord: 1, voices: vec![0, 1] },
+            Finding { fault: Fault::ParallelOctaves, chord: 1, voices: vec![0, 3] },
+        ]);
+    }
+
+    #[test]
+    fn hidden_and_spacing1() {
+        let checker = CounterpointChecker::default();
+        let findings = checker.check(&progression(&["C3 G3 E4", "D3 F3 D5"])).unwrap();
+        assert_eq!(findings.iter().map(|finding| finding.to_string()).collect::<Vec<String>>(), vec![
+            "wide spacing at chord 1 (voices 1, 2)",
+            "hidden octaves at chord 1 (voices 0, 2)",
+        ]);
+    }
+
+    #[test]
+    fn leading_tone_and_leap1() {
+        let checker = CounterpointChecker { leading_tone: Some(PitchClass(11)), ..Default::default() };
+        let findings = checker.check(&progression(&["G2 D4 G4 B4", "C3 E4 G4 A4", "C3 E4 G4 G4"])).unwrap();
+        assert_eq!(findings, vec![
+            Finding { fault: Fault::UnresolvedLeadingTone, chord: 1, voices: vec![3] },
+        ]);
+        assert!(checker.check_step(&VoiceLeading::new(vec![]), 1).is_empty());
+        let findings = checker.check(&progression(&["C3 E4 G4 C5", "C3 E4 G4 A5", "C3 E4 G4 B5"])).unwrap();
+        assert_eq!(findings, vec![
+            Finding { fault: Fault::WideSpacing, chord: 1, voices: vec![2, 3] },
+            Finding { fault: Fault::WideSpacing, chord: 2, voices: vec![2, 3] },
+            Finding { fault: Fault::UnrecoveredLeap, chord: 1, voices: vec![3] },
+        ]);
+    }
+}
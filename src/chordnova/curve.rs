@@ -0,0 +1,63 @@
+/// A target value changing over a progression, linear between keyframes
+///
+/// Keyframes are (position, value) pairs, the position running from 0 (first generated chord)
+/// to 1 (last generated chord); before the first and after the last keyframe the value is held.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricCurve {
+    keyframes: Vec<(f64, f64)>,
+}
+
+#[allow(dead_code)]
+impl MetricCurve {
+    pub fn new(mut keyframes: Vec<(f64, f64)>) -> Self {
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        MetricCurve { keyframes }
+    }
+
+    pub fn constant(value: f64) -> Self {
+        MetricCurve { keyframes: vec![(0.0, value)] }
+    }
+
+    /// evenly spaced keyframes, e.g. one value per chord
+    pub fn from_values(values: &[f64]) -> Self {
+        let last = values.len().saturating_sub(1).max(1) as f64;
+        MetricCurve { keyframes: values.iter().enumerate().map(|(i, value)| (i as f64 / last, *value)).collect() }
+    }
+
+    pub fn value_at(&self, position: f64) -> f64 {
+        let after = self.keyframes.iter().position(|(at, _)| *at > position);
+        match after {
+            None => self.keyframes.last().map_or(0.0, |(_, value)| *value),
+            Some(0) => self.keyframes[0].1,
+            Some(i) => {
+                let ((x0, y0), (x1, y1)) = (self.keyframes[i - 1], self.keyframes[i]);
+                y0 + (y1 - y0) * (position - x0) / (x1 - x0)
+            }
+        }
+    }
+
+    /// position of step `step` (0-based) of `steps`
+    pub fn position(step: usize, steps: usize) -> f64 {
+        match steps {
+            0 | 1 => 0.0,
+            _ => step as f64 / (steps - 1) as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_at1() {
+        let arc = MetricCurve::new(vec![(1.0, 0.2), (0.0, 0.2), (0.5, 0.8)]);
+        assert_eq!(arc.value_at(0.0), 0.2);
+        assert!((arc.value_at(0.25) - 0.5).abs() < 1e-9);
+        assert_eq!(arc.value_at(0.5), 0.8);
+        assert_eq!(arc.value_at(1.5), 0.2);
+        assert_eq!(MetricCurve::constant(3.0).value_at(0.7), 3.0);
+        let steps = MetricCurve::from_values(&[1.0, 2.0, 4.0]);
+        assert_eq!((0..3).map(|step| steps.value_at(MetricCurve::position(step, 3))).collect::<Vec<f64>>(), vec![1.0, 2.0, 4.0]);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::chordnova::chord::CNChord;
+use crate::chordnova::generator::CNGenerator;
+use crate::chordnova::pathfinder::{key, shortest_path_where, Steps};
+use crate::chordnova::pitch::Pitch;
+use crate::chordnova::progression::Progression;
+
+/// Where a cycle has to end
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Closure {
+    /// back on the starting chord
+    Exact,
+    /// on the starting chord transposed by this many semitones, so that the cycle repeats as a sequence
+    Transposition(i8),
+    /// on any transposition of the starting chord by at most this many semitones either way, itself included
+    AnyTransposition(u8),
+}
+
+impl Closure {
+    fn shifts(&self) -> Vec<i8> {
+        match self {
+            Closure::Exact => vec![0],
+            Closure::Transposition(shift) => vec![*shift],
+            Closure::AnyTransposition(max_shift) => {
+                let max_shift = (*max_shift).min(127) as i8;
+                (-max_shift..=max_shift).collect()
+            }
+        }
+    }
+}
+
+/// `chord` moved by `shift` semitones, if it stays within MIDI range
+fn transpose(chord: &CNChord, shift: i8) -> Option<CNChord> {
+    let pitches = chord._pitches.iter()
+        .map(|pitch| u8::try_from(i16::from(pitch.0) + i16::from(shift)).ok().filter(|x| *x < 128).map(Pitch))
+        .collect::<Option<Vec<Pitch>>>()?;
+    Some(CNChord::from_notes(&pitches, false))
+}
+
+/// Closed progressions of `length` moves from `start`, every move a candidate of `generator`
+///
+/// For every transposition allowed by `closure` the cheapest cycle by total sv is kept; the results are sorted by cost.
+/// A move never repeats the previous chord, and neither the start nor the closing chord occurs in between,
+/// so a cycle does not stand still or contain a shorter one.
+#[allow(dead_code)]
+pub fn find_cycles(generator: &CNGenerator, start: &CNChord, length: usize, closure: Closure) -> Vec<Progression> {
+    if length == 0 {
+        return vec![];
+    }
+    let start_key = key(start);
+    let mut cycles = closure.shifts().into_iter()
+        .filter_map(|shift| transpose(start, shift))
+        .filter_map(|end| {
+            let end_key = key(&end);
+            shortest_path_where(generator, start, &end, Steps::Exactly(length), |prev, chord, moves| {
+                let chord_key = key(chord);
+                chord_key != key(prev) && (moves == length || (chord_key != start_key && chord_key != end_key))
+            })
+        })
+        .collect::<Vec<Progression>>();
+    cycles.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use itertools::Itertools;
+    use crate::chordnova::generator::GeneratorConfig;
+
+    fn generator() -> CNGenerator {
+        CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() })
+    }
+
+    #[test]
+    fn exact1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let cycles = find_cycles(&generator(), &c_major, 3, Closure::Exact);
+        assert_eq!(cycles.len(), 1);
+        let cycle = &cycles[0];
+        assert_eq!(cycle.to_string(), "<Progression: [C4, E4, G4] -> [C4, E-4, G4] -> [C4, D4, G4] -> [C4, E4, G4], cost: 4.00>");
+        assert!(cycle.chords[1..3].iter().all(|chord| key(chord) != key(&c_major)));
+        assert!(cycle.chords.iter().tuple_windows().all(|(a, b)| key(a) != key(b)));
+        assert!(find_cycles(&generator(), &c_major, 0, Closure::Exact).is_empty());
+    }
+
+    #[test]
+    fn transposition1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let cycles = find_cycles(&generator(), &c_major, 3, Closure::Transposition(2));
+        assert_eq!(cycles.len(), 1);
+        // every voice rises by two semitones, and no more
+        assert_eq!(cycles[0].to_string(), "<Progression: [C4, E4, G4] -> [C4, E4, G#4] -> [C#4, E4, G#4] -> [D4, F#4, A4], cost: 6.00>");
+        let cycles = find_cycles(&generator(), &c_major, 2, Closure::AnyTransposition(1));
+        assert_eq!(cycles.iter().map(|cycle| cycle.to_string()).collect::<Vec<String>>(), vec![
+            "<Progression: [C4, E4, G4] -> [C4, E-4, G4] -> [C4, E4, G4], cost: 2.00>",
+            "<Progression: [C4, E4, G4] -> [B3, E4, G4] -> [B3, E-4, F#4], cost: 3.00>",
+            "<Progression: [C4, E4, G4] -> [C4, E4, G#4] -> [C#4, F4, G#4], cost: 3.00>",
+        ]);
+        // the top note cannot rise past the range
+        assert!(find_cycles(&generator(), &c_major, 2, Closure::Transposition(7)).is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+use std::f64::consts::PI;
+use crate::chordnova::chord::CNChord;
+use crate::chordnova::pcset::PitchClassSet;
+
+/// Components f1 - f6 of the discrete Fourier transform of pitch-class content
+///
+/// Their magnitudes are read as chord qualities:
+/// f1 chromaticity, f2 dyadicity, f3 triadicity, f4 octatonicity, f5 diatonicity, f6 whole-tone quality.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PitchClassDft {
+    /// (real, imaginary) part of f1 - f6
+    pub components: [(f64, f64); 6],
+}
+
+impl PitchClassDft {
+    /// `weights[p]` is the weight of pitch class p
+    pub fn from_weights(weights: &[f64; 12]) -> Self {
+        let mut components = [(0f64, 0f64); 6];
+        for (k, component) in components.iter_mut().enumerate() {
+            for (pitch_class, weight) in weights.iter().enumerate() {
+                let angle = -2.0 * PI * ((k + 1) * pitch_class) as f64 / 12.0;
+                component.0 += weight * angle.cos();
+                component.1 += weight * angle.sin();
+            }
+        }
+        PitchClassDft { components }
+    }
+
+    pub fn from_set(set: &PitchClassSet) -> Self {
+        let mut weights = [0f64; 12];
+        for pitch_class in set.pitch_classes() {
+            weights[usize::from(pitch_class)] = 1.0;
+        }
+        PitchClassDft::from_weights(&weights)
+    }
+
+    /// With `weighted`, every pitch class counts as often as it is doubled in the chord
+    pub fn from_chord(chord: &CNChord, weighted: bool) -> Self {
+        match weighted {
+            true => {
+                let mut weights = [0f64; 12];
+                for pitch in &chord._pitches {
+                    weights[usize::from(pitch.get_pitch_class().0)] += 1.0;
+                }
+                PitchClassDft::from_weights(&weights)
+            }
+            false => PitchClassDft::from_set(&chord.get_pitch_class_set())
+        }
+    }
+
+    /// fk for k from 1 to 6, `None` for any other k
+    fn component(&self, k: usize) -> Option<(f64, f64)> {
+        k.checked_sub(1).and_then(|index| self.components.get(index)).copied()
+    }
+
+    /// |fk| for k from 1 to 6, `None` for any other k
+    #[allow(dead_code)]
+    pub fn magnitude(&self, k: usize) -> Option<f64> {
+        self.component(k).map(|(re, im)| re.hypot(im))
+    }
+
+    /// phase of fk in radians for k from 1 to 6, `None` for any other k
+    #[allow(dead_code)]
+    pub fn phase(&self, k: usize) -> Option<f64> {
+        self.component(k).map(|(re, im)| im.atan2(re))
+    }
+
+    #[allow(dead_code)]
+    pub fn magnitudes(&self) -> [f64; 6] {
+        let mut magnitudes = [0f64; 6];
+        for (magnitude, (re, im)) in magnitudes.iter_mut().zip(self.components.iter()) {
+            *magnitude = re.hypot(*im);
+        }
+        magnitudes
+    }
+
+    /// Euclidean distance of the magnitudes; 0 between transpositions and inversions
+    #[allow(dead_code)]
+    pub fn magnitude_distance(&self, other: &PitchClassDft) -> f64 {
+        self.magnitudes().iter().zip(other.magnitudes().iter())
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    /// Euclidean distance of the complex components, which also tells transpositions apart
+    #[allow(dead_code)]
+    pub fn distance(&self, other: &PitchClassDft) -> f64 {
+        self.components.iter().zip(other.components.iter())
+            .map(|((re1, im1), (re2, im2))| (re1 - re2).powi(2) + (im1 - im2).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn magnitude1() {
+        let whole_tone = PitchClassDft::from_set(&PitchClassSet::from_integers(&[0, 2, 4, 6, 8, 10]));
+        assert!((whole_tone.magnitude(6).unwrap() - 6.0).abs() < 1e-9);
+        assert!(whole_tone.magnitude(1).unwrap() < 1e-9);
+        assert_eq!(whole_tone.magnitude(0), None);
+        assert_eq!(whole_tone.magnitude(7), None);
+        let diatonic = PitchClassDft::from_set(&PitchClassSet::from_integers(&[0, 2, 4, 5, 7, 9, 11]));
+        let magnitudes = diatonic.magnitudes();
+        assert!(magnitudes.iter().all(|magnitude| *magnitude <= magnitudes[4]));
+        let augmented = PitchClassDft::from_set(&PitchClassSet::from_integers(&[0, 4, 8]));
+        assert!((augmented.magnitude(3).unwrap() - 3.0).abs() < 1e-9);
+        assert!(augmented.phase(3).unwrap().abs() < 1e-9);
+    }
+
+    #[test]
+    fn weighted1() {
+        let c_major: CNChord = CNChord::from_str("C3 G3 C4 E4").unwrap();
+        let plain = PitchClassDft::from_chord(&c_major, false);
+        let weighted = PitchClassDft::from_chord(&c_major, true);
+        assert_eq!(plain, PitchClassDft::from_set(&PitchClassSet::from_integers(&[0, 4, 7])));
+        assert!(weighted.magnitude(1) > plain.magnitude(1));
+    }
+
+    #[test]
+    fn distance1() {
+        let c_major = PitchClassDft::from_set(&PitchClassSet::from_integers(&[0, 4, 7]));
+        let a_minor = PitchClassDft::from_set(&PitchClassSet::from_integers(&[9, 0, 4]));
+        let f_sharp_major = PitchClassDft::from_set(&PitchClassSet::from_integers(&[6, 10, 1]));
+        assert!(c_major.magnitude_distance(&a_minor) < 1e-9);
+        assert!(c_major.magnitude_distance(&f_sharp_major) < 1e-9);
+        assert!(c_major.distance(&a_minor) > 1e-3);
+        assert!(c_major.distance(&a_minor) < c_major.distance(&f_sharp_major));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::chordnova::pitch::Pitch;
+
+/// Range a single voice may sing or play in, inclusive
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct VoiceRange {
+    pub lowest: Pitch,
+    pub highest: Pitch,
+}
+
+#[allow(dead_code)]
+impl VoiceRange {
+    pub const fn new(lowest: u8, highest: u8) -> Self {
+        VoiceRange { lowest: Pitch(lowest), highest: Pitch(highest) }
+    }
+
+    pub fn contains(&self, pitch: &Pitch) -> bool {
+        (self.lowest.0..=self.highest.0).contains(&pitch.0)
+    }
+}
+
+/// Preset ensembles; their voice ranges are practical rather than extreme ones
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ensemble {
+    /// bass E2 - D4, tenor C3 - A4, alto G3 - D5, soprano C4 - G5
+    Satb,
+    /// cello C2 - E5, viola C3 - C6, second violin G3 - G6, first violin G3 - C7
+    StringQuartet,
+    /// tuba D1 - F4, trombone E2 - C5, horn F2 - F5, second trumpet F#3 - C6, first trumpet F#3 - D6 (sounding)
+    BrassQuintet,
+    /// the number of notes taken by either hand, left hand E1 - G4, right hand F3 - C7
+    Piano { left_hand: usize, right_hand: usize },
+}
+
+#[allow(dead_code)]
+impl Ensemble {
+    /// range of every voice, from the lowest voice upwards
+    pub fn voice_ranges(&self) -> Vec<VoiceRange> {
+        match self {
+            Ensemble::Satb => vec![VoiceRange::new(40, 62), VoiceRange::new(48, 69), VoiceRange::new(55, 74), VoiceRange::new(60, 79)],
+            Ensemble::StringQuartet => vec![VoiceRange::new(36, 76), VoiceRange::new(48, 84), VoiceRange::new(55, 91), VoiceRange::new(55, 96)],
+            Ensemble::BrassQuintet => vec![
+                VoiceRange::new(26, 65), VoiceRange::new(40, 72), VoiceRange::new(41, 77), VoiceRange::new(54, 84), VoiceRange::new(54, 86),
+            ],
+            Ensemble::Piano { left_hand, right_hand } => [VoiceRange::new(28, 67)].repeat(*left_hand).into_iter()
+                .chain([VoiceRange::new(53, 96)].repeat(*right_hand))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn voice_ranges1() {
+        let satb = Ensemble::Satb.voice_ranges();
+        assert_eq!(satb.len(), 4);
+        assert!(satb[1].contains(&Pitch(69)) && !satb[1].contains(&Pitch(70)));
+        let piano = Ensemble::Piano { left_hand: 1, right_hand: 3 }.voice_ranges();
+        assert_eq!(piano.len(), 4);
+        assert!(piano[0].contains(&Pitch(36)) && !piano[1].contains(&Pitch(36)));
+    }
+}
@@ -0,0 +1,317 @@
+use crate::chordnova::pitchparser::pest::Parser;
+use crate::chordnova::pitchparser::pest::iterators::Pair;
+use crate::chordnova::pitchparser::Rule;
+use crate::chordnova::pitchparser::PitchParser;
+
+use std::fmt;
+use std::str::FromStr;
+use crate::chordnova::chord::{CNChord, ParseCNChordError};
+use crate::chordnova::pcset::PitchClassSet;
+use crate::chordnova::pitch::Pitch;
+use crate::chordnova::scale::Scale;
+use crate::chordnova::voicing::{Position, VoicingConstraints, Voicings};
+
+const NATURALS: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+/// largest figure accepted, a compound ninth beyond the double octave being unheard of
+const MAX_FIGURE: u8 = 15;
+
+/// letter of the tonic of a seven-note `key`, and the pitch classes of its degrees from the tonic
+///
+/// Every degree takes the next letter; the tonic takes the letter that needs the fewest accidentals,
+/// so that e.g. C minor is spelt with E-, A- and B-.
+fn spell_key(key: &Scale) -> Option<(usize, Vec<u8>)> {
+    let scale = key.pitch_class_set();
+    let degrees = (0..12).map(|i| (key.tonic.0 + i) % 12).filter(|pitch_class| scale.contains(*pitch_class)).collect::<Vec<u8>>();
+    if degrees.len() != 7 || degrees[0] != key.tonic.0 {
+        return None;
+    }
+    let accidentals = |letter: usize| degrees.iter().enumerate()
+        .map(|(k, pitch_class)| {
+            let distance = (pitch_class + 12 - NATURALS[(letter + k) % 7]) % 12;
+            u32::from(distance.min(12 - distance))
+        })
+        .sum::<u32>();
+    let letter = (0..7).min_by_key(|letter| accidentals(*letter))?;
+    Some((letter, degrees))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FigureAccidental {
+    Sharp,
+    Flat,
+    Natural,
+}
+
+/// A generic interval above the bass, e.g. #6
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Figure {
+    pub interval: u8,
+    pub accidental: Option<FigureAccidental>,
+}
+
+impl Figure {
+    fn new(interval: u8) -> Self {
+        Figure { interval, accidental: None }
+    }
+}
+
+/// A bass note with its figures as written
+#[derive(Clone)]
+pub struct FiguredBass {
+    pub bass: Pitch,
+    /// letter of the bass, 0 (C) - 6 (B)
+    pub letter: usize,
+    pub figures: Vec<Figure>,
+}
+
+impl FiguredBass {
+    /// the figures written out in full, e.g. 6 -> 6/3 and 4/2 -> 6/4/2, from the top
+    pub fn full_figures(&self) -> Vec<Figure> {
+        let mut intervals = self.figures.iter().map(|figure| figure.interval).collect::<Vec<u8>>();
+        intervals.sort_unstable_by(|a, b| b.cmp(a));
+        let full: Vec<u8> = match intervals.as_slice() {
+            [] | [5] | [3] | [5, 3] => vec![5, 3],
+            [6] | [6, 3] => vec![6, 3],
+            [7] | [7, 3] | [7, 5, 3] => vec![7, 5, 3],
+            [6, 5] | [6, 5, 3] => vec![6, 5, 3],
+            [4, 3] | [6, 4, 3] => vec![6, 4, 3],
+            [2] | [4, 2] | [6, 4, 2] => vec![6, 4, 2],
+            [9] | [9, 5, 3] => vec![9, 5, 3],
+            _ => intervals,
+        };
+        full.into_iter()
+            .map(|interval| Figure {
+                interval,
+                accidental: self.figures.iter().find(|figure| figure.interval == interval).and_then(|figure| figure.accidental),
+            })
+            .collect()
+    }
+
+    /// pitch classes above the bass in `key`: the scale degree of every figure's letter, altered by its accidental
+    ///
+    /// Keys other than seven-note scales have no key signature, so their figures read as naturals.
+    pub fn upper_pitch_classes(&self, key: &Scale) -> PitchClassSet {
+        let spelling = spell_key(key);
+        PitchClassSet::from_integers(&self.full_figures().iter().map(|figure| {
+            // letters above the bass, the bass itself being a unison
+            let steps = (usize::from(figure.interval) + 6) % 7;
+            let natural = NATURALS[(self.letter + steps) % 7];
+            let in_key = match &spelling {
+                Some((tonic_letter, degrees)) => degrees[(self.letter + 7 - tonic_letter + steps) % 7],
+                None => natural
+            };
+            match figure.accidental {
+                None => in_key,
+                Some(FigureAccidental::Sharp) => (in_key + 1) % 12,
+                Some(FigureAccidental::Flat) => (in_key + 11) % 12,
+                Some(FigureAccidental::Natural) => natural,
+            }
+        }).collect::<Vec<u8>>())
+    }
+}
+
+impl fmt::Display for FiguredBass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.bass)?;
+        if !self.figures.is_empty() {
+            let figures = self.figures.iter().map(|figure| format!("{}{}", match figure.accidental {
+                None => "",
+                Some(FigureAccidental::Sharp) => "#",
+                Some(FigureAccidental::Flat) => "-",
+                Some(FigureAccidental::Natural) => "n",
+            }, figure.interval)).collect::<Vec<String>>();
+            write!(f, " {}", figures.join("/"))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseFiguredBassError {
+    msg: String,
+}
+
+impl fmt::Display for ParseFiguredBassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<ParseFiguredBassError: {}>", self.msg)
+    }
+}
+
+fn parse_figure(pair: Pair<Rule>) -> Result<Figure, ParseFiguredBassError> {
+    let mut figure = Figure::new(3);
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::FIGURE_ACCIDENTAL => figure.accidental = match inner.as_str() {
+                "#" => Some(FigureAccidental::Sharp),
+                "-" => Some(FigureAccidental::Flat),
+                _ => Some(FigureAccidental::Natural),
+            },
+            _ => figure.interval = inner.as_str().parse().ok()
+                .filter(|interval| (1..=MAX_FIGURE).contains(interval))
+                .ok_or_else(|| ParseFiguredBassError { msg: format!("Figure {} not within 1 - {}", inner.as_str(), MAX_FIGURE) })?,
+        }
+    }
+    Ok(figure)
+}
+
+fn parse_figured_bass(pair: Pair<Rule>) -> Result<FiguredBass, ParseFiguredBassError> {
+    let mut inner = pair.into_inner();
+    let bass_pair = inner.next().unwrap();
+    let letter = bass_pair.as_str().get(..1).and_then(|step| "CDEFGAB".find(step))
+        .ok_or_else(|| ParseFiguredBassError { msg: format!("No note name in {}", bass_pair.as_str()) })?;
+    let bass = Pitch::from_str(bass_pair.as_str()).map_err(|e| ParseFiguredBassError { msg: e.to_string() })?;
+    let figures = match inner.next() {
+        Some(figures) => figures.into_inner().map(parse_figure).collect::<Result<Vec<Figure>, ParseFiguredBassError>>()?,
+        None => vec![]
+    };
+    Ok(FiguredBass { bass, letter, figures })
+}
+
+/// Parse a line of figured bass such as "C3 E3 6 F3 6/4 G2 7 C3"
+#[allow(dead_code)]
+pub fn parse_figured_bass_line(s: &str) -> Result<Vec<FiguredBass>, ParseFiguredBassError> {
+    let mut pairs = PitchParser::parse(Rule::FULL_FIGURED_BASS_LINE, s)
+        .map_err(|e| ParseFiguredBassError { msg: e.to_string() })?;
+    match pairs.next() {
+        Some(pair) if pair.as_rule() == Rule::FIGURED_BASS_LINE => pair.into_inner().map(parse_figured_bass).collect(),
+        _ => Err(ParseFiguredBassError { msg: format!("Not a figured bass line: {}", s) })
+    }
+}
+
+impl FromStr for FiguredBass {
+    type Err = ParseFiguredBassError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut line = parse_figured_bass_line(s)?;
+        match line.len() {
+            1 => Ok(line.remove(0)),
+            n => Err(ParseFiguredBassError { msg: format!("Expected one bass note, found {}", n) })
+        }
+    }
+}
+
+/// Options for realising the upper voices of a figured bass
+pub struct RealisationConfig {
+    /// number of upper voices
+    pub voices: usize,
+    /// lowest note of the upper voices
+    pub lowest: Pitch,
+    /// highest note of the upper voices
+    pub highest: Pitch,
+    pub position: Position,
+}
+
+impl Default for RealisationConfig {
+    fn default() -> Self {
+        RealisationConfig {
+            voices: 3,
+            lowest: Pitch(55),
+            highest: Pitch(79),
+            position: Position::Closed,
+        }
+    }
+}
+
+/// Realise the upper voices of `line` in `key`
+///
+/// The first chord is the lowest voicing within range; every following one is the voicing
+/// reached from the previous chord with the smallest sv according to `CNChord::find_vec`.
+#[allow(dead_code)]
+pub fn realise(line: &[FiguredBass], key: &Scale, config: &RealisationConfig) -> Result<Vec<CNChord>, ParseCNChordError> {
+    let mut realisation: Vec<CNChord> = vec![];
+    for figured_bass in line {
+        let mut pitch_classes = figured_bass.upper_pitch_classes(key);
+        // double the bass when the figures leave voices free
+        if pitch_classes.len() < config.voices {
+            pitch_classes = PitchClassSet(pitch_classes.0 | (1 << (figured_bass.bass.0 % 12)));
+        }
+        let above_bass = figured_bass.bass.0.checked_add(1).filter(|note| *note <= 127)
+            .ok_or_else(|| ParseCNChordError::new(format!("No room for upper voices above {}", figured_bass)))?;
+        let voicings = Voicings::new(VoicingConstraints {
+            pitch_classes,
+            lowest: Pitch(config.lowest.0.max(above_bass)),
+            highest: config.highest,
+            min_voices: config.voices,
+            max_voices: config.voices,
+            complete: pitch_classes.len() <= config.voices,
+            position: config.position,
+            ..Default::default()
+        });
+        let chord = match realisation.last() {
+            None => voicings.into_iter().next(),
+            Some(prev) => voicings
+                .filter_map(|chord| {
+                    let (matched_prev, matched_chord) = prev.find_vec(&chord, false, false).ok()?;
+                    Some((matched_prev.diff(&matched_chord).ok()?.sv, chord))
+                })
+                .min_by_key(|(sv, _)| *sv)
+                .map(|(_, chord)| chord)
+        };
+        match chord {
+            Some(chord) => realisation.push(chord),
+            None => return Err(ParseCNChordError::new(format!("No voicing of {} above {}", pitch_classes, figured_bass)))
+        }
+    }
+    Ok(realisation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chordnova::pitch::PitchClass;
+    use crate::chordnova::scale::ScaleType;
+
+    #[test]
+    fn parse1() {
+        let line = parse_figured_bass_line("C3 E3 6 F3 6/4 G2 7 B-2 #6/4/3").unwrap();
+        assert_eq!(line.len(), 5);
+        assert_eq!(line[1].figures, vec![Figure::new(6)]);
+        assert_eq!(line[3].bass.0, 43);
+        assert_eq!(line[4].figures[0], Figure { interval: 6, accidental: Some(FigureAccidental::Sharp) });
+        assert_eq!(line[4].to_string(), "B-2 #6/4/3");
+        assert_eq!(FiguredBass::from_str("A2 #").unwrap().figures, vec![Figure { interval: 3, accidental: Some(FigureAccidental::Sharp) }]);
+        assert!(FiguredBass::from_str("C 6").is_err());
+        assert!(parse_figured_bass_line("C3 6//4").is_err());
+        assert!(parse_figured_bass_line("C3 300").is_err());
+        assert!(parse_figured_bass_line("C3 0").is_err());
+        assert!(parse_figured_bass_line("C3 #0/4").is_err());
+        assert_eq!(FiguredBass::from_str("C3 15").unwrap().figures, vec![Figure::new(15)]);
+    }
+
+    #[test]
+    fn upper_pitch_classes1() {
+        let c_major = Scale::new(PitchClass(0), ScaleType::Ionian);
+        let line = parse_figured_bass_line("C3 E3 6 G2 7 F3 4/2").unwrap();
+        let sets = line.iter().map(|figured_bass| figured_bass.upper_pitch_classes(&c_major).pitch_classes()).collect::<Vec<Vec<u8>>>();
+        assert_eq!(sets, vec![vec![4, 7], vec![0, 7], vec![2, 5, 11], vec![2, 7, 11]]);
+        let g_major = Scale::new(PitchClass(7), ScaleType::Ionian);
+        assert_eq!(FiguredBass::from_str("F#3 6/5").unwrap().upper_pitch_classes(&g_major).pitch_classes(), vec![0, 2, 9]);
+        let d_minor = Scale::new(PitchClass(2), ScaleType::Aeolian);
+        assert_eq!(FiguredBass::from_str("A2 #").unwrap().upper_pitch_classes(&d_minor).pitch_classes(), vec![1, 4]);
+        // flat keys: E- and B- come from the key, not from the nearest natural
+        let c_minor = Scale::new(PitchClass(0), ScaleType::Aeolian);
+        let line = parse_figured_bass_line("C3 A-2 6 G2 #").unwrap();
+        let sets = line.iter().map(|figured_bass| figured_bass.upper_pitch_classes(&c_minor).pitch_classes()).collect::<Vec<Vec<u8>>>();
+        assert_eq!(sets, vec![vec![3, 7], vec![0, 5], vec![2, 11]]);
+        let f_major = Scale::new(PitchClass(5), ScaleType::Ionian);
+        let line = parse_figured_bass_line("B-2 C3 7 E3 6/5").unwrap();
+        let sets = line.iter().map(|figured_bass| figured_bass.upper_pitch_classes(&f_major).pitch_classes()).collect::<Vec<Vec<u8>>>();
+        assert_eq!(sets, vec![vec![2, 5], vec![4, 7, 10], vec![0, 7, 10]]);
+    }
+
+    #[test]
+    fn realise1() {
+        let c_major = Scale::new(PitchClass(0), ScaleType::Ionian);
+        let line = parse_figured_bass_line("C3 G2 7 C3").unwrap();
+        let realisation = realise(&line, &c_major, &RealisationConfig::default()).unwrap();
+        assert_eq!(realisation[0].to_string(), "G3, C4, E4");
+        for (figured_bass, chord) in line.iter().zip(realisation.iter()) {
+            assert_eq!(chord.t_size(), 3);
+            assert!(chord._pitches.iter().all(|pitch| *pitch > figured_bass.bass));
+        }
+        assert_eq!(realisation[1].get_pitch_class_set(), PitchClassSet::from_integers(&[2, 5, 11]));
+        assert_eq!(realisation[2].get_pitch_class_set(), realisation[0].get_pitch_class_set());
+        let highest = FiguredBass { bass: Pitch(127), letter: 4, figures: vec![] };
+        let config = RealisationConfig { highest: Pitch(127), ..Default::default() };
+        assert_eq!(realise(&[highest], &c_major, &config).err().unwrap().to_string(), "<ParseCNChordError: No room for upper voices above G9>");
+    }
+}
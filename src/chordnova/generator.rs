@@ -0,0 +1,625 @@
+/*
+   Generation logics of ChordNova, separated from the chord model.
+   See also main.cpp / chord.cpp of the original C++ implementation.
+ */
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use itertools::Itertools;
+use crate::chordnova::chord::{CNChord, VoiceMatcher};
+use crate::chordnova::ensemble::VoiceRange;
+use crate::chordnova::markov::Prior;
+use crate::chordnova::metric::{Metric, MetricBound};
+use crate::chordnova::pcset::PitchClassSet;
+use crate::chordnova::pitch::Pitch;
+use crate::chordnova::progression::Progression;
+use crate::chordnova::root::RootAlgorithm;
+use crate::chordnova::scale::Scale;
+use crate::chordnova::voiceleading::VoiceLeading;
+use crate::chordnova::voicing::{VoicingConstraints, Voicings};
+
+/// Parameters of the generator
+pub struct GeneratorConfig {
+    /// lowest note of a candidate
+    pub lowest: Pitch,
+    /// highest note of a candidate
+    pub highest: Pitch,
+    /// n_min; least number of notes of a candidate
+    pub min_voices: usize,
+    /// n_max; most number of notes of a candidate
+    pub max_voices: usize,
+    /// range of every voice from the bass upwards, e.g. `Ensemble::Satb.voice_ranges()`;
+    /// when given, candidates have exactly this many voices
+    pub voice_ranges: Option<Vec<VoiceRange>>,
+    /// scale or key the notes of a candidate are taken from; `None` allows any note
+    pub scale: Option<Scale>,
+    /// number of notes of a candidate that may lie outside of `scale`
+    pub chromatic_notes: usize,
+    /// voices of the previous chord, counted from the bass, that have to be held as the same voice
+    /// of the candidate, e.g. 0 for a bass pedal that no note may go below
+    pub fixed_voices: Vec<usize>,
+    /// pitches every candidate has to contain, held if the previous chord contains them, e.g. a pedal
+    pub fixed_pitches: Vec<Pitch>,
+    /// c_min; least number of notes in common with the previous chord
+    pub min_common_notes: usize,
+    /// vl_max; largest movement of a single voice
+    pub vl_max: u16,
+    /// pairing of the voices of the previous chord with those of a candidate
+    pub voice_matcher: VoiceMatcher,
+    /// whether voices may cross
+    pub allow_crossing: bool,
+    /// whether a voice may move past the previous position of its neighbour
+    pub allow_overlap: bool,
+    /// algorithm for r, used by `root_motions`
+    pub root_algorithm: RootAlgorithm,
+    /// allowed root movements in semitones upwards (0 - 11); `None` allows any
+    pub root_motions: Option<Vec<u8>>,
+    /// ranges the indicators of a candidate have to lie in
+    pub bounds: Vec<MetricBound>,
+    /// indicator to sort candidates by, smallest first
+    pub sort_by: Metric,
+    /// learned transition model added to `sort_by`, favouring likely candidates
+    pub prior: Option<Prior>,
+    /// further conditions a candidate has to meet
+    pub filters: Vec<Box<dyn CandidateFilter>>,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            lowest: Pitch(48),
+            highest: Pitch(72),
+            min_voices: 3,
+            max_voices: 4,
+            voice_ranges: None,
+            scale: None,
+            chromatic_notes: 0,
+            fixed_voices: vec![],
+            fixed_pitches: vec![],
+            min_common_notes: 0,
+            vl_max: 4,
+            voice_matcher: VoiceMatcher::ChordPairs,
+            allow_crossing: true,
+            allow_overlap: true,
+            root_algorithm: RootAlgorithm::Hindemith,
+            root_motions: None,
+            bounds: vec![],
+            sort_by: Metric::VoiceLeading,
+            prior: None,
+            filters: vec![],
+        }
+    }
+}
+
+/// Extra condition on a candidate, checked after the built-in constraints
+pub trait CandidateFilter {
+    fn accept(&self, prev: &CNChord, candidate: &Candidate) -> bool;
+}
+
+/// A chord accepted by the generator, together with the voice leading that reaches it
+#[allow(dead_code)]
+pub struct Candidate {
+    pub chord: CNChord,
+    pub voice_leading: VoiceLeading,
+}
+
+/// How far a `CandidateStream` has got
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// chords checked so far
+    pub examined: usize,
+    /// candidates yielded so far
+    pub accepted: usize,
+}
+
+/// called with the progress of a `CandidateStream`
+pub type ProgressCallback = Box<dyn FnMut(&Progress)>;
+
+/// Options of a `CandidateStream`
+#[derive(Default)]
+pub struct StreamOptions {
+    /// stop after this many candidates
+    pub limit: Option<usize>,
+    /// called after every chord checked
+    pub progress: Option<ProgressCallback>,
+    /// stop as soon as this is set, e.g. from another thread
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+/// The candidates following a chord, checked one at a time as they are enumerated
+pub struct CandidateStream<'a> {
+    generator: &'a CNGenerator,
+    prev: &'a CNChord,
+    chords: Box<dyn Iterator<Item=CNChord> + 'a>,
+    options: StreamOptions,
+    progress: Progress,
+}
+
+impl Iterator for CandidateStream<'_> {
+    type Item = Candidate;
+
+    fn next(&mut self) -> Option<Candidate> {
+        if self.options.limit.is_some_and(|limit| self.progress.accepted >= limit) {
+            return None;
+        }
+        loop {
+            if self.options.cancel.as_ref().is_some_and(|cancel| cancel.load(AtomicOrdering::Relaxed)) {
+                return None;
+            }
+            let chord = self.chords.next()?;
+            self.progress.examined += 1;
+            let candidate = self.generator.evaluate(self.prev, chord, false);
+            if candidate.is_some() {
+                self.progress.accepted += 1;
+            }
+            if let Some(progress) = self.options.progress.as_mut() {
+                progress(&self.progress);
+            }
+            if candidate.is_some() {
+                return candidate;
+            }
+        }
+    }
+}
+
+/// a candidate in the heap of `CandidateStream::top_k`, ordered by score, then by the order found
+struct Ranked(f64, usize, Candidate);
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+#[allow(dead_code)]
+impl CandidateStream<'_> {
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    /// the `k` candidates with the lowest `CNGenerator::score`, sorted by it,
+    /// keeping no more than `k` candidates in memory
+    pub fn top_k(self, k: usize) -> Vec<Candidate> {
+        if k == 0 {
+            return vec![];
+        }
+        let (generator, prev) = (self.generator, self.prev);
+        let mut heap: BinaryHeap<Ranked> = BinaryHeap::new();
+        for (i, candidate) in self.enumerate() {
+            heap.push(Ranked(generator.score(prev, &candidate), i, candidate));
+            if heap.len() > k {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec().into_iter().map(|Ranked(_, _, candidate)| candidate).collect()
+    }
+}
+
+/// cheapest paths to a chord of a step: (cost, index of the chord of the previous step, rank of the path there)
+type Paths = Vec<(f64, usize, usize)>;
+
+pub struct CNGenerator {
+    pub config: GeneratorConfig,
+}
+
+impl CNGenerator {
+    #[allow(dead_code)]
+    pub fn new(config: GeneratorConfig) -> Self {
+        CNGenerator { config }
+    }
+
+    /// pitches of `fixed_voices` and `fixed_pitches`
+    fn fixed(&self, prev: &CNChord) -> Vec<Pitch> {
+        let voices = prev._pitches.iter().copied().sorted().collect::<Vec<Pitch>>();
+        self.config.fixed_voices.iter()
+            .filter_map(|i| voices.get(*i).copied())
+            .chain(self.config.fixed_pitches.iter().copied())
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    /// every chord within the configured range, number of notes and scale that can follow `prev`
+    fn chords(&self, prev: Option<&CNChord>, soprano: Option<Pitch>) -> impl Iterator<Item=CNChord> + '_ {
+        let scale = self.config.scale.as_ref().map(|scale| scale.pitch_class_set());
+        let pitch_classes = match scale {
+            Some(scale) if self.config.chromatic_notes == 0 => scale,
+            _ => PitchClassSet(0xfff)
+        };
+        Voicings::new(VoicingConstraints {
+            pitch_classes,
+            lowest: self.config.lowest,
+            highest: self.config.highest,
+            min_voices: self.config.min_voices,
+            max_voices: self.config.max_voices,
+            voice_ranges: self.config.voice_ranges.clone(),
+            required: prev.map_or(vec![], |prev| self.fixed(prev)),
+            common_with: prev.map_or(vec![], |prev| prev._pitches.clone()),
+            min_common: prev.map_or(0, |_| self.config.min_common_notes),
+            soprano,
+            ..Default::default()
+        }).filter(move |chord| scale.is_none_or(|scale| {
+            chord._pitches.iter().filter(|pitch| !scale.contains(pitch.0)).count() <= self.config.chromatic_notes
+        }))
+    }
+
+    /// Check whether `chord` may follow `prev`
+    pub fn evaluate(&self, prev: &CNChord, chord: CNChord, in_substitution: bool) -> Option<Candidate> {
+        let voice_leading = VoiceLeading::find_with(prev, &chord, in_substitution, self.config.voice_matcher).ok()?;
+        if voice_leading.max_movement() > self.config.vl_max
+            || (!self.config.allow_crossing && voice_leading.has_crossing())
+            || (!self.config.allow_overlap && voice_leading.has_overlap())
+            || prev.common_notes(&chord) < self.config.min_common_notes {
+            return None;
+        }
+        let held = |pitch: &Pitch| voice_leading.from.iter().zip(voice_leading.to.iter()).any(|(from, to)| from == pitch && to == pitch);
+        if !self.fixed(prev).iter().all(|pitch| chord._pitches.contains(pitch) && (!prev._pitches.contains(pitch) || held(pitch))) {
+            return None;
+        }
+        let (prev_voices, voices) = (prev._pitches.iter().sorted().collect::<Vec<&Pitch>>(), chord._pitches.iter().sorted().collect::<Vec<&Pitch>>());
+        if !self.config.fixed_voices.iter().all(|i| prev_voices.get(*i).is_none_or(|pitch| voices.get(*i) == Some(pitch))) {
+            return None;
+        }
+        if let Some(root_motions) = &self.config.root_motions {
+            let prev_root = self.config.root_algorithm.find_root(prev)?.root;
+            let root = self.config.root_algorithm.find_root(&chord)?.root;
+            if !root_motions.contains(&((root.0 + 12 - prev_root.0) % 12)) {
+                return None;
+            }
+        }
+        let candidate = Candidate { chord, voice_leading };
+        if !self.config.bounds.iter().all(|bound| (bound.min..=bound.max).contains(&bound.metric.evaluate(prev, &candidate))) {
+            return None;
+        }
+        match self.config.filters.iter().all(|filter| filter.accept(prev, &candidate)) {
+            true => Some(candidate),
+            false => None
+        }
+    }
+
+    /// cost of `candidate` under the prior, 0 if there is none
+    pub fn prior_cost(&self, prev: &CNChord, candidate: &Candidate) -> f64 {
+        self.config.prior.as_ref().map_or(0.0, |prior| prior.cost(prev, &candidate.chord, &candidate.voice_leading))
+    }
+
+    /// `sort_by` of `candidate`, plus the cost of the prior if there is one
+    pub fn score(&self, prev: &CNChord, candidate: &Candidate) -> f64 {
+        self.config.sort_by.evaluate(prev, candidate) + self.prior_cost(prev, candidate)
+    }
+
+    /// the candidates following `prev` in the order enumerated, without sorting
+    #[allow(dead_code)]
+    pub fn stream<'a>(&'a self, prev: &'a CNChord, options: StreamOptions) -> CandidateStream<'a> {
+        CandidateStream {
+            generator: self,
+            prev,
+            chords: Box::new(self.chords(Some(prev), None)),
+            options,
+            progress: Progress::default(),
+        }
+    }
+
+    /// all candidates following `prev`, sorted by `score`
+    #[allow(dead_code)]
+    pub fn generate(&self, prev: &CNChord) -> Vec<Candidate> {
+        self.stream(prev, StreamOptions::default()).top_k(usize::MAX)
+    }
+
+    /// candidates replacing `target` in the progression `prev` -> `target`, closest to `target` first
+    #[allow(dead_code)]
+    pub fn substitute(&self, prev: &CNChord, target: &CNChord) -> Vec<Candidate> {
+        self.chords(Some(prev), None)
+            .filter(|chord| chord._pitches != target._pitches)
+            .filter_map(|chord| {
+                let (matched_target, matched_chord) = target.find_vec(&chord, false, true).ok()?;
+                let distance = matched_target.diff(&matched_chord).ok()?.sv;
+                self.evaluate(prev, chord, true).map(|candidate| (distance, candidate))
+            })
+            .sorted_by_key(|(distance, candidate)| (*distance, candidate.voice_leading.diff.sv))
+            .map(|(_, candidate)| candidate)
+            .collect()
+    }
+
+    /// the `k` cheapest progressions whose top voice follows `melody`, a step costing its `sort_by` value
+    ///
+    /// Viterbi search over the steps of the melody, keeping the `k` cheapest ways to reach every chord.
+    #[allow(dead_code)]
+    pub fn harmonise(&self, melody: &[Pitch], k: usize) -> Vec<Progression> {
+        let Some(first) = melody.first() else {
+            return vec![];
+        };
+        let mut layers: Vec<Vec<(CNChord, Paths)>> = vec![
+            self.chords(None, Some(*first)).map(|chord| (chord, vec![(0.0, 0, 0)])).collect()
+        ];
+        for soprano in &melody[1..] {
+            let mut layer: Vec<(CNChord, Paths)> = vec![];
+            let mut index: HashMap<Vec<u8>, usize> = HashMap::new();
+            for (i, (prev, paths)) in layers.last().unwrap().iter().enumerate() {
+                for chord in self.chords(Some(prev), Some(*soprano)) {
+                    let Some(candidate) = self.evaluate(prev, chord, false) else {
+                        continue;
+                    };
+                    let cost = self.score(prev, &candidate);
+                    let j = *index.entry(candidate.chord._pitches.iter().map(|pitch| pitch.0).collect()).or_insert_with(|| {
+                        layer.push((candidate.chord, vec![]));
+                        layer.len() - 1
+                    });
+                    let paths_to = &mut layer[j].1;
+                    paths_to.extend(paths.iter().enumerate().map(|(rank, (total, _, _))| (total + cost, i, rank)));
+                    paths_to.sort_by(|a, b| a.0.total_cmp(&b.0));
+                    paths_to.truncate(k);
+                }
+            }
+            layers.push(layer);
+        }
+        let last = layers.last().unwrap();
+        last.iter().enumerate()
+            .flat_map(|(i, (_, paths))| paths.iter().enumerate().map(move |(rank, (cost, _, _))| (*cost, i, rank)))
+            .sorted_by(|a, b| a.0.total_cmp(&b.0))
+            .take(k)
+            .map(|(cost, mut i, mut rank)| {
+                let mut chords = vec![];
+                for layer in layers.iter().rev() {
+                    let (chord, paths) = &layer[i];
+                    chords.push(chord.clone());
+                    (_, i, rank) = paths[rank];
+                }
+                chords.reverse();
+                Progression { chords, cost }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chordnova::counterpoint::CounterpointChecker;
+    use crate::chordnova::ensemble::Ensemble;
+    use crate::chordnova::markov::MarkovModel;
+    use crate::chordnova::pitch::PitchClass;
+    use crate::chordnova::scale::ScaleType;
+    use crate::chordnova::tension::{SetharesRoughness, Spectrum, TensionModel};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    #[test]
+    fn generate1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() });
+        let candidates = generator.generate(&c_major);
+        assert_eq!(candidates[0].chord.to_string(), "C4, E4, G4");
+        assert!(candidates.iter().all(|candidate| candidate.voice_leading.max_movement() <= 2));
+        assert!(candidates.iter().any(|candidate| candidate.chord.to_string() == "C4, F4, A4"));
+    }
+
+    #[test]
+    fn generate2() {
+        let c_major: CNChord = CNChord::from_str("C4 D4 G4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, ..Default::default() });
+        let with_overlap = generator.generate(&c_major);
+        assert!(with_overlap.iter().any(|candidate| candidate.voice_leading.has_overlap()));
+        let generator = CNGenerator::new(GeneratorConfig { allow_overlap: false, ..generator.config });
+        let without_overlap = generator.generate(&c_major);
+        assert!(without_overlap.len() < with_overlap.len());
+        assert!(without_overlap.iter().all(|candidate| !candidate.voice_leading.has_overlap()));
+    }
+
+    #[test]
+    fn crossing1() {
+        // written in voice order with the middle voice above the top one;
+        // candidates are voiced from the bottom up, so reaching any of them swaps the two voices
+        let c_major: CNChord = CNChord::from_str("C4 G4 E4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, ..Default::default() });
+        let with_crossing = generator.generate(&c_major);
+        assert!(!with_crossing.is_empty());
+        assert!(with_crossing.iter().all(|candidate| candidate.voice_leading.crossings == vec![(1, 2)]));
+        let generator = CNGenerator::new(GeneratorConfig { allow_crossing: false, ..generator.config });
+        assert!(generator.generate(&c_major).is_empty());
+        assert!(!generator.generate(&CNChord::from_str("C4 E4 G4").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn root_motion1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, root_motions: Some(vec![5]), ..Default::default() });
+        let candidates = generator.generate(&c_major);
+        assert!(candidates.iter().any(|candidate| candidate.chord.to_string() == "C4, F4, A4"));
+        assert!(candidates.iter().all(|candidate| RootAlgorithm::Hindemith.find_root(&candidate.chord).unwrap().root.0 == 5));
+    }
+
+    #[test]
+    fn tension1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let roughness = || Box::new(SetharesRoughness { spectrum: Spectrum::default() });
+        let bound = MetricBound { metric: Metric::Tension(roughness()), min: 0.0, max: 1.0 };
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, bounds: vec![bound], sort_by: Metric::Tension(roughness()), ..Default::default() });
+        let candidates = generator.generate(&c_major);
+        let tensions = candidates.iter().map(|candidate| roughness().tension(&candidate.chord)).collect::<Vec<f64>>();
+        assert!(!tensions.is_empty() && tensions.iter().all(|tension| *tension <= 1.0));
+        assert!(tensions.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn substitute1() {
+        let f_major: CNChord = CNChord::from_str("F3 A3 C4").unwrap();
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 7, allow_overlap: false, ..Default::default() });
+        let candidates = generator.substitute(&f_major, &c_major);
+        assert!(!candidates.is_empty());
+        assert!(candidates.iter().all(|candidate| candidate.chord.to_string() != "C4, E4, G4" && !candidate.voice_leading.has_overlap()));
+    }
+
+    #[test]
+    fn voice_ranges1() {
+        let c_major: CNChord = CNChord::from_str("C3 G3 E4 C5").unwrap();
+        let config = GeneratorConfig { lowest: Pitch(40), highest: Pitch(79), vl_max: 2, voice_ranges: Some(Ensemble::Satb.voice_ranges()), ..Default::default() };
+        let generator = CNGenerator::new(config);
+        let candidates = generator.generate(&c_major);
+        assert!(candidates.iter().any(|candidate| candidate.chord.to_string() == "B2, G3, D4, D5"));
+        assert!(candidates.iter().all(|candidate| candidate.chord.t_size() == 4 && candidate.chord._pitches[1].0 <= 69));
+        let substitutes = generator.substitute(&c_major, &CNChord::from_str("F3 A3 C4 F4").unwrap());
+        assert!(!substitutes.is_empty() && substitutes.iter().all(|candidate| candidate.chord._pitches[1].0 <= 69));
+    }
+
+    #[test]
+    fn scale1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let scale = Scale::new(PitchClass(0), ScaleType::Ionian);
+        let config = GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, scale: Some(scale.clone()), ..Default::default() };
+        let diatonic = CNGenerator::new(config).generate(&c_major);
+        assert!(diatonic.iter().any(|candidate| candidate.chord.to_string() == "B3, D4, G4"));
+        assert!(diatonic.iter().all(|candidate| candidate.chord._pitches.iter().all(|pitch| scale.contains(pitch))));
+        let config = GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, scale: Some(scale.clone()), chromatic_notes: 1, ..Default::default() };
+        let chromatic = CNGenerator::new(config).generate(&c_major);
+        assert!(chromatic.iter().any(|candidate| candidate.chord.to_string() == "C#4, E4, A4"));
+        assert!(chromatic.iter().all(|candidate| candidate.chord._pitches.iter().filter(|pitch| !scale.contains(pitch)).count() <= 1));
+    }
+
+    #[test]
+    fn fixed1() {
+        let c_major: CNChord = CNChord::from_str("C3 G3 E4 C5").unwrap();
+        let config = GeneratorConfig { lowest: Pitch(48), highest: Pitch(74), min_voices: 4, max_voices: 4, vl_max: 2, fixed_voices: vec![0], fixed_pitches: vec![Pitch(72)], ..Default::default() };
+        let candidates = CNGenerator::new(config).generate(&c_major);
+        assert!(candidates.iter().any(|candidate| candidate.chord.to_string() == "C3, A3, F4, C5"));
+        assert!(candidates.iter().all(|candidate| candidate.chord._pitches[0] == Pitch(48) && candidate.chord._pitches.contains(&Pitch(72))));
+        let config = GeneratorConfig { lowest: Pitch(48), highest: Pitch(74), min_voices: 4, max_voices: 4, vl_max: 2, min_common_notes: 2, ..Default::default() };
+        let candidates = CNGenerator::new(config).generate(&c_major);
+        assert!(!candidates.is_empty());
+        assert!(candidates.iter().all(|candidate| c_major.common_notes(&candidate.chord) >= 2));
+    }
+
+    #[test]
+    fn fixed2() {
+        // the range reaches below the pedal, and a fifth voice lets a note split off underneath it
+        let c_major: CNChord = CNChord::from_str("C3 G3 E4 C5").unwrap();
+        let config = GeneratorConfig { lowest: Pitch(43), highest: Pitch(74), min_voices: 4, max_voices: 5, vl_max: 2, ..Default::default() };
+        let free = CNGenerator::new(config).generate(&c_major);
+        assert!(free.iter().any(|candidate| candidate.chord._pitches.contains(&Pitch(48)) && candidate.chord._pitches[0] < Pitch(48)));
+        let config = GeneratorConfig { lowest: Pitch(43), highest: Pitch(74), min_voices: 4, max_voices: 5, vl_max: 2, fixed_voices: vec![0], ..Default::default() };
+        let pedal = CNGenerator::new(config).generate(&c_major);
+        assert!(!pedal.is_empty());
+        assert!(pedal.iter().all(|candidate| candidate.chord._pitches[0] == Pitch(48)));
+    }
+
+    #[test]
+    fn harmonise1() {
+        let melody = [Pitch(72), Pitch(71), Pitch(72)];
+        let generator = CNGenerator::new(GeneratorConfig {
+            lowest: Pitch(55),
+            highest: Pitch(72),
+            min_voices: 3,
+            max_voices: 3,
+            vl_max: 2,
+            scale: Some(Scale::new(PitchClass(0), ScaleType::Ionian)),
+            ..Default::default()
+        });
+        let progressions = generator.harmonise(&melody, 3);
+        assert_eq!(progressions.len(), 3);
+        assert!(progressions.windows(2).all(|pair| pair[0].cost <= pair[1].cost));
+        for progression in &progressions {
+            assert_eq!(progression.chords.iter().map(|chord| chord._pitches.iter().max().unwrap().0).collect::<Vec<u8>>(), vec![72, 71, 72]);
+            let cost = progression.chords.windows(2).map(|pair| VoiceLeading::find(&pair[0], &pair[1], false).unwrap().diff.sv as f64).sum::<f64>();
+            assert_eq!(cost, progression.cost);
+        }
+        // only the melody moves
+        assert_eq!(progressions[0].cost, 2.0);
+        assert!(generator.harmonise(&[Pitch(72), Pitch(60)], 1).is_empty());
+    }
+
+    #[test]
+    fn prior1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let mut model = MarkovModel::default();
+        model.train_text("C4 E4 G4 | C4 F4 A4\nC4 E4 G4 | C4 F4 A4").unwrap();
+        let config = GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() };
+        assert_eq!(CNGenerator::new(config).generate(&c_major)[0].chord.to_string(), "C4, E4, G4");
+        let config = GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, prior: Some(Prior { model, weight: 10.0 }), ..Default::default() };
+        assert_eq!(CNGenerator::new(config).generate(&c_major)[0].chord.to_string(), "C4, F4, A4");
+    }
+
+    #[test]
+    fn prior2() {
+        let melody = [Pitch(72), Pitch(72), Pitch(72)];
+        let c_major = Scale::new(PitchClass(0), ScaleType::Ionian);
+        let config = GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, scale: Some(c_major.clone()), ..Default::default() };
+        let chords = |generator: CNGenerator| generator.harmonise(&melody, 1)[0].chords.iter().map(|chord| chord.to_string()).collect::<Vec<String>>();
+        // holding the first chord costs nothing
+        let held = chords(CNGenerator::new(config));
+        assert!(held.iter().all_equal());
+        let mut model = MarkovModel::default();
+        model.train_text("E4 G4 C5 | F4 A4 C5 | E4 G4 C5").unwrap();
+        let config = GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, scale: Some(c_major), prior: Some(Prior { model, weight: 10.0 }), ..Default::default() };
+        assert_eq!(chords(CNGenerator::new(config)), vec!["E4, G4, C5", "F4, A4, C5", "E4, G4, C5"]);
+    }
+
+    #[test]
+    fn filter1() {
+        let c_major: CNChord = CNChord::from_str("C3 G3 E4 C5").unwrap();
+        let config = GeneratorConfig { lowest: Pitch(48), highest: Pitch(74), min_voices: 4, max_voices: 4, vl_max: 2, ..Default::default() };
+        let unfiltered = CNGenerator::new(config).generate(&c_major);
+        assert!(unfiltered.iter().any(|candidate| candidate.chord.to_string() == "D3, A3, F4, D5"));
+        let config = GeneratorConfig { lowest: Pitch(48), highest: Pitch(74), min_voices: 4, max_voices: 4, vl_max: 2, filters: vec![Box::new(CounterpointChecker::default())], ..Default::default() };
+        let filtered = CNGenerator::new(config).generate(&c_major);
+        assert!(!filtered.is_empty() && filtered.len() < unfiltered.len());
+        assert!(filtered.iter().all(|candidate| candidate.chord.to_string() != "D3, A3, F4, D5"));
+    }
+
+    #[test]
+    fn stream1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() });
+        let all = generator.generate(&c_major);
+        let mut stream = generator.stream(&c_major, StreamOptions::default());
+        assert_eq!(stream.by_ref().count(), all.len());
+        assert_eq!(stream.progress().accepted, all.len());
+        assert!(stream.progress().examined > all.len());
+        let scores = |candidates: &[Candidate]| candidates.iter().map(|candidate| generator.score(&c_major, candidate)).collect::<Vec<f64>>();
+        let top = generator.stream(&c_major, StreamOptions::default()).top_k(3);
+        assert_eq!(scores(&top), scores(&all[..3]));
+        let limited = generator.stream(&c_major, StreamOptions { limit: Some(5), ..Default::default() });
+        assert_eq!(limited.count(), 5);
+    }
+
+    #[test]
+    fn cancel1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() });
+        let cancel = Arc::new(AtomicBool::new(true));
+        assert_eq!(generator.stream(&c_major, StreamOptions { cancel: Some(cancel.clone()), ..Default::default() }).count(), 0);
+        // cancelled from the progress callback after 10 chords
+        cancel.store(false, AtomicOrdering::Relaxed);
+        let examined = Rc::new(Cell::new(0));
+        let (flag, count) = (cancel.clone(), examined.clone());
+        let options = StreamOptions {
+            progress: Some(Box::new(move |progress: &Progress| {
+                count.set(progress.examined);
+                if progress.examined == 10 {
+                    flag.store(true, AtomicOrdering::Relaxed);
+                }
+            })),
+            cancel: Some(cancel),
+            ..Default::default()
+        };
+        let stream = generator.stream(&c_major, options);
+        assert!(stream.count() <= 10);
+        assert_eq!(examined.get(), 10);
+    }
+}
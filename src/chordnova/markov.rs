@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use itertools::Itertools;
+use crate::chordnova::chord::{CNChord, ParseCNChordError};
+use crate::chordnova::midi::{read_chords, MidiError};
+use crate::chordnova::pcset::PitchClassSet;
+use crate::chordnova::root::RootAlgorithm;
+use crate::chordnova::voiceleading::VoiceLeading;
+
+/// What the model remembers of a move from one chord to the next
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Transition {
+    /// set class of the first chord
+    pub from: PitchClassSet,
+    /// set class of the second chord
+    pub to: PitchClassSet,
+    /// root movement in semitones upwards, if both chords have a root
+    pub root_motion: Option<u8>,
+    /// vec; movement of every voice as matched by `VoiceLeading::find`
+    pub vec: Vec<i16>,
+}
+
+/// First-order Markov model of chord transitions
+pub struct MarkovModel {
+    /// algorithm for the roots of `Transition::root_motion`
+    pub root_algorithm: RootAlgorithm,
+    /// added to the count of every transition, seen or not
+    pub smoothing: f64,
+    counts: HashMap<Transition, u32>,
+    /// number of transitions and of distinct transitions leaving every set class
+    totals: HashMap<PitchClassSet, (u32, u32)>,
+}
+
+impl Default for MarkovModel {
+    fn default() -> Self {
+        MarkovModel {
+            root_algorithm: RootAlgorithm::Hindemith,
+            smoothing: 1.0,
+            counts: HashMap::new(),
+            totals: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseMarkovModelError {
+    msg: String,
+}
+
+impl fmt::Display for ParseMarkovModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<ParseMarkovModelError: {}>", self.msg)
+    }
+}
+
+#[allow(dead_code)]
+impl MarkovModel {
+    pub fn transition(&self, prev: &CNChord, chord: &CNChord, voice_leading: &VoiceLeading) -> Transition {
+        let root_motion = match (self.root_algorithm.find_root(prev), self.root_algorithm.find_root(chord)) {
+            (Some(prev_root), Some(root)) => Some((root.root.0 + 12 - prev_root.root.0) % 12),
+            _ => None
+        };
+        Transition {
+            from: prev.get_pitch_class_set().set_class(),
+            to: chord.get_pitch_class_set().set_class(),
+            root_motion,
+            vec: voice_leading.diff.diff_vec.clone(),
+        }
+    }
+
+    fn add(&mut self, transition: Transition, count: u32) {
+        let total = self.totals.entry(transition.from).or_insert((0, 0));
+        total.0 += count;
+        let seen = self.counts.entry(transition).or_insert(0);
+        if *seen == 0 {
+            total.1 += 1;
+        }
+        *seen += count;
+    }
+
+    /// count every move of `progression`
+    pub fn train(&mut self, progression: &[CNChord]) {
+        for (prev, chord) in progression.iter().tuple_windows() {
+            if let Ok(voice_leading) = VoiceLeading::find(prev, chord, false) {
+                let transition = self.transition(prev, chord, &voice_leading);
+                self.add(transition, 1);
+            }
+        }
+    }
+
+    /// train on text progressions, one per line with chords separated by "|", e.g. "C4 E4 G4 | F3 A3 C4";
+    /// blank lines and lines starting with "#" are skipped
+    pub fn train_text(&mut self, text: &str) -> Result<(), ParseCNChordError> {
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let progression = line.split('|').map(|chord| CNChord::from_str(chord.trim())).collect::<Result<Vec<CNChord>, ParseCNChordError>>()?;
+            self.train(&progression);
+        }
+        Ok(())
+    }
+
+    /// train on the chords of a Standard MIDI File
+    pub fn train_midi(&mut self, bytes: &[u8]) -> Result<(), MidiError> {
+        self.train(&read_chords(bytes)?);
+        Ok(())
+    }
+
+    /// smoothed log-probability of `transition` among the transitions leaving its first set class
+    pub fn log_likelihood(&self, transition: &Transition) -> f64 {
+        let count = f64::from(self.counts.get(transition).copied().unwrap_or(0));
+        // a set class the corpus never leaves is judged against every transition, rather than being free to leave
+        let (total, distinct) = self.totals.get(&transition.from).copied()
+            .unwrap_or_else(|| self.totals.values().fold((0, 0), |(total, distinct), (t, d)| (total + t, distinct + d)));
+        // one more outcome stands for every transition not seen yet
+        ((count + self.smoothing) / (f64::from(total) + self.smoothing * f64::from(distinct + 1))).ln()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ParseMarkovModelError> {
+        fs::read_to_string(path)
+            .map_err(|e| ParseMarkovModelError { msg: e.to_string() })?
+            .parse()
+    }
+}
+
+/// "smoothing;s" and "root algorithm;name", then one transition per line: "from;to;root motion or -;vec;count",
+/// sets and vectors separated by commas
+impl fmt::Display for MarkovModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "smoothing;{}", self.smoothing)?;
+        writeln!(f, "root algorithm;{:?}", self.root_algorithm)?;
+        writeln!(f, "# from;to;root motion;vec;count")?;
+        let lines = self.counts.iter()
+            .map(|(transition, count)| format!("{};{};{};{};{}",
+                                               transition.from.pitch_classes().iter().join(","),
+                                               transition.to.pitch_classes().iter().join(","),
+                                               transition.root_motion.map_or(String::from("-"), |motion| motion.to_string()),
+                                               transition.vec.iter().join(","),
+                                               count))
+            .sorted();
+        for line in lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MarkovModel {
+    type Err = ParseMarkovModelError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |line: &str| ParseMarkovModelError { msg: format!("Invalid line {}", line) };
+        let mut model = MarkovModel::default();
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let set = |field: &str| field.split(',').filter(|x| !x.is_empty())
+                .map(|x| x.parse::<u8>()).collect::<Result<Vec<u8>, _>>()
+                .map(|pitch_classes| PitchClassSet::from_integers(&pitch_classes));
+            match line.split(';').collect::<Vec<&str>>().as_slice() {
+                ["smoothing", smoothing] => model.smoothing = smoothing.parse().map_err(|_| error(line))?,
+                ["root algorithm", "Hindemith"] => model.root_algorithm = RootAlgorithm::Hindemith,
+                ["root algorithm", "Parncutt"] => model.root_algorithm = RootAlgorithm::Parncutt,
+                [from, to, root_motion, vec, count] => {
+                    let transition = Transition {
+                        from: set(from).map_err(|_| error(line))?,
+                        to: set(to).map_err(|_| error(line))?,
+                        root_motion: match *root_motion {
+                            "-" => None,
+                            motion => Some(motion.parse().map_err(|_| error(line))?)
+                        },
+                        vec: vec.split(',').filter(|x| !x.is_empty()).map(|x| x.parse::<i16>()).collect::<Result<Vec<i16>, _>>().map_err(|_| error(line))?,
+                    };
+                    model.add(transition, count.parse().map_err(|_| error(line))?);
+                }
+                _ => return Err(error(line))
+            }
+        }
+        Ok(model)
+    }
+}
+
+/// A trained model blended into the generator's ranking
+pub struct Prior {
+    pub model: MarkovModel,
+    /// cost of every unit of surprisal, i.e. of negative log-likelihood
+    pub weight: f64,
+}
+
+impl Prior {
+    pub fn cost(&self, prev: &CNChord, chord: &CNChord, voice_leading: &VoiceLeading) -> f64 {
+        -self.weight * self.model.log_likelihood(&self.model.transition(prev, chord, voice_leading))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORPUS: &str = "
+        # plagal and authentic cadences
+        C4 E4 G4 | C4 F4 A4 | C4 E4 G4
+        C4 E4 G4 | C4 F4 A4 | B3 D4 G4 | C4 E4 G4
+    ";
+
+    #[test]
+    fn train1() {
+        let mut model = MarkovModel::default();
+        model.train_text(CORPUS).unwrap();
+        let c_major = CNChord::from_str("C4 E4 G4").unwrap();
+        let f_major = CNChord::from_str("C4 F4 A4").unwrap();
+        let a_minor = CNChord::from_str("C4 E4 A4").unwrap();
+        let seen = model.transition(&c_major, &f_major, &VoiceLeading::find(&c_major, &f_major, false).unwrap());
+        assert_eq!(seen.root_motion, Some(5));
+        assert_eq!(seen.vec, vec![0, 1, 2]);
+        let unseen = model.transition(&c_major, &a_minor, &VoiceLeading::find(&c_major, &a_minor, false).unwrap());
+        // every chord is a 3-11; 2 of the 5 moves, and of 4 distinct transitions, are C -> F: (2 + 1) / (5 + 5)
+        assert!((model.log_likelihood(&seen) - 0.3_f64.ln()).abs() < 1e-9);
+        assert!(model.log_likelihood(&unseen) < model.log_likelihood(&seen));
+        let b_diminished = CNChord::from_str("B3 D4 F4").unwrap();
+        let from_unseen = model.transition(&b_diminished, &c_major, &VoiceLeading::find(&b_diminished, &c_major, false).unwrap());
+        assert!((model.log_likelihood(&from_unseen) - model.log_likelihood(&unseen)).abs() < 1e-9);
+        assert!(model.train_text("C4 E4 G4 | H4").is_err());
+    }
+
+    #[test]
+    fn save1() {
+        let mut model = MarkovModel::default();
+        model.train_text(CORPUS).unwrap();
+        let text = model.to_string();
+        assert!(text.contains("0,3,7;0,3,7;5;0,1,2;2"));
+        let loaded: MarkovModel = text.parse().unwrap();
+        assert_eq!(loaded.to_string(), text);
+        let path = std::env::temp_dir().join("chordnova_markov_save1.txt");
+        model.save(&path).unwrap();
+        assert_eq!(MarkovModel::load(&path).unwrap().to_string(), text);
+        fs::remove_file(&path).unwrap();
+        assert!("0,3,7;0,3,7;x;0;1".parse::<MarkovModel>().is_err());
+        assert!("root algorithm;Rameau".parse::<MarkovModel>().is_err());
+    }
+
+    #[test]
+    fn save2() {
+        let mut model = MarkovModel { root_algorithm: RootAlgorithm::Parncutt, smoothing: 0.25, ..Default::default() };
+        model.train_text(CORPUS).unwrap();
+        let loaded: MarkovModel = model.to_string().parse().unwrap();
+        assert_eq!(loaded.root_algorithm, RootAlgorithm::Parncutt);
+        assert_eq!(loaded.smoothing, 0.25);
+        let c_major = CNChord::from_str("C4 E4 G4").unwrap();
+        let a_minor = CNChord::from_str("C4 E4 A4").unwrap();
+        let voice_leading = VoiceLeading::find(&c_major, &a_minor, false).unwrap();
+        let transition = model.transition(&c_major, &a_minor, &voice_leading);
+        assert_eq!(loaded.transition(&c_major, &a_minor, &voice_leading), transition);
+        assert_eq!(loaded.log_likelihood(&transition), model.log_likelihood(&transition));
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+use crate::chordnova::chord::CNChord;
+use crate::chordnova::consonance::ConsonanceMeasure;
+use crate::chordnova::curve::MetricCurve;
+use crate::chordnova::dft::PitchClassDft;
+use crate::chordnova::generator::Candidate;
+use crate::chordnova::tension::TensionModel;
+
+/// Indicators of a candidate the generator can bound and sort by
+#[allow(dead_code)]
+pub enum Metric {
+    /// sv; total movement of the voices
+    VoiceLeading,
+    /// t, with the given model
+    Tension(Box<dyn TensionModel>),
+    /// one of the extended consonance measures
+    Consonance(ConsonanceMeasure),
+    /// |fk| of the pitch-class content, built by `Metric::dft_magnitude`
+    DftMagnitude(DftComponent),
+}
+
+/// fk of `Metric::DftMagnitude`, k from 1 to 6; optionally weighted by doubling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DftComponent {
+    k: usize,
+    weighted: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MetricError {
+    msg: String,
+}
+
+impl fmt::Display for MetricError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<MetricError: {}>", self.msg)
+    }
+}
+
+impl Metric {
+    /// |fk| of the pitch-class content; k has to lie between 1 and 6
+    #[allow(dead_code)]
+    pub fn dft_magnitude(k: usize, weighted: bool) -> Result<Metric, MetricError> {
+        match k {
+            1..=6 => Ok(Metric::DftMagnitude(DftComponent { k, weighted })),
+            _ => Err(MetricError { msg: format!("No DFT component f{}, only f1 - f6", k) })
+        }
+    }
+
+    pub fn evaluate(&self, _prev: &CNChord, candidate: &Candidate) -> f64 {
+        match self {
+            Metric::VoiceLeading => f64::from(candidate.voice_leading.diff.sv),
+            Metric::Tension(model) => model.tension(&candidate.chord),
+            Metric::Consonance(measure) => measure.consonance(&candidate.chord),
+            Metric::DftMagnitude(component) => PitchClassDft::from_chord(&candidate.chord, component.weighted).magnitudes()[component.k - 1],
+        }
+    }
+}
+
+/// Keep candidates whose `metric` lies within `min..=max`
+pub struct MetricBound {
+    pub metric: Metric,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Steer a search towards candidates whose `metric` follows `curve`
+pub struct MetricTarget {
+    pub metric: Metric,
+    pub curve: MetricCurve,
+    /// cost of every unit of distance to the curve
+    pub weight: f64,
+    /// largest distance to the curve a candidate may have
+    pub tolerance: Option<f64>,
+}
+
+impl MetricTarget {
+    /// distance of `candidate` to the curve at `position`
+    pub fn deviation(&self, prev: &CNChord, candidate: &Candidate, position: f64) -> f64 {
+        (self.metric.evaluate(prev, candidate) - self.curve.value_at(position)).abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::chordnova::voiceleading::VoiceLeading;
+
+    #[test]
+    fn dft_magnitude1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let augmented: CNChord = CNChord::from_str("C4 E4 G#4").unwrap();
+        let candidate = Candidate { voice_leading: VoiceLeading::find(&c_major, &augmented, false).unwrap(), chord: augmented };
+        let triadicity = Metric::dft_magnitude(3, false).unwrap();
+        assert!((triadicity.evaluate(&c_major, &candidate) - 3.0).abs() < 1e-9);
+        assert!(Metric::dft_magnitude(0, false).is_err());
+        assert_eq!(Metric::dft_magnitude(7, true).err().unwrap().to_string(), "<MetricError: No DFT component f7, only f1 - f6>");
+    }
+}
@@ -0,0 +1,211 @@
+/*
+   A minimal Standard MIDI File reader, enough to extract the chords of a piece.
+   Only note on / note off events are kept; the percussion channel is skipped.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::chordnova::chord::CNChord;
+use crate::chordnova::pitch::Pitch;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MidiError {
+    msg: String,
+}
+
+impl MidiError {
+    fn new(msg: &str) -> Self {
+        MidiError { msg: msg.to_string() }
+    }
+}
+
+impl fmt::Display for MidiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<MidiError: {}>", self.msg)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], MidiError> {
+        let taken = self.bytes.get(self.position..self.position + n).ok_or(MidiError::new("Unexpected end of file"))?;
+        self.position += n;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, MidiError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, MidiError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// variable-length quantity of at most four bytes
+    fn vlq(&mut self) -> Result<u32, MidiError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | u32::from(byte & 0x7f);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(MidiError::new("Variable-length quantity too long"))
+    }
+}
+
+/// (tick, note, whether it starts) of every note event of a track
+fn read_track(track: &[u8]) -> Result<Vec<(u64, u8, bool)>, MidiError> {
+    let mut reader = Reader { bytes: track, position: 0 };
+    let mut events = vec![];
+    let mut tick = 0_u64;
+    let mut running_status = None;
+    while !reader.is_empty() {
+        tick += u64::from(reader.vlq()?);
+        let mut status = reader.u8()?;
+        // sysex and meta events cancel the running status
+        match status {
+            0xff => {
+                let kind = reader.u8()?;
+                let length = reader.vlq()? as usize;
+                reader.take(length)?;
+                if kind == 0x2f {
+                    break;
+                }
+                running_status = None;
+                continue;
+            }
+            0xf0 | 0xf7 => {
+                let length = reader.vlq()? as usize;
+                reader.take(length)?;
+                running_status = None;
+                continue;
+            }
+            _ => {}
+        }
+        // a data byte repeats the previous status
+        let first = if status < 0x80 {
+            let data = status;
+            status = running_status.ok_or(MidiError::new("Running status without a previous status"))?;
+            data
+        } else {
+            running_status = Some(status);
+            reader.u8()?
+        };
+        let channel = status & 0x0f;
+        match status & 0xf0 {
+            0x80 | 0x90 => {
+                let velocity = reader.u8()?;
+                if channel != 9 {
+                    events.push((tick, first, status & 0xf0 == 0x90 && velocity > 0));
+                }
+            }
+            0xa0 | 0xb0 | 0xe0 => {
+                reader.u8()?;
+            }
+            0xc0 | 0xd0 => {}
+            _ => return Err(MidiError::new(&format!("Unknown status {:#x}", status)))
+        }
+    }
+    Ok(events)
+}
+
+/// Every change of the sounding notes of a Standard MIDI File, all tracks merged, as a chord
+pub fn read_chords(bytes: &[u8]) -> Result<Vec<CNChord>, MidiError> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(4)? != b"MThd" {
+        return Err(MidiError::new("Not a Standard MIDI File"));
+    }
+    let header_length = reader.u32()? as usize;
+    let header = reader.take(header_length)?;
+    let tracks = match header {
+        [_, _, high, low, ..] => u16::from_be_bytes([*high, *low]),
+        _ => return Err(MidiError::new("Header too short"))
+    };
+    // events of a tick, note offs before note ons
+    let mut events: BTreeMap<u64, Vec<(bool, u8)>> = BTreeMap::new();
+    for _ in 0..tracks {
+        let kind = reader.take(4)?;
+        let length = reader.u32()? as usize;
+        let chunk = reader.take(length)?;
+        if kind != b"MTrk" {
+            continue;
+        }
+        for (tick, note, on) in read_track(chunk)? {
+            events.entry(tick).or_default().push((on, note));
+        }
+    }
+    let mut sounding = [0_u16; 128];
+    let mut chords: Vec<CNChord> = vec![];
+    for (_, mut tick_events) in events {
+        tick_events.sort();
+        for (on, note) in tick_events {
+            let count = &mut sounding[usize::from(note & 0x7f)];
+            *count = if on { count.saturating_add(1) } else { count.saturating_sub(1) };
+        }
+        let pitches = (0..128_u8).filter(|note| sounding[usize::from(*note)] > 0).map(Pitch).collect::<Vec<Pitch>>();
+        if !pitches.is_empty() && chords.last().is_none_or(|chord| chord._pitches != pitches) {
+            chords.push(CNChord::from_notes(&pitches, false));
+        }
+    }
+    Ok(chords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a file of format 0 with a single track
+    fn smf(track: &[u8]) -> Vec<u8> {
+        let mut bytes = b"MThd".to_vec();
+        bytes.extend([0, 0, 0, 6, 0, 0, 0, 1, 0, 96]);
+        bytes.extend(b"MTrk");
+        bytes.extend((track.len() as u32).to_be_bytes());
+        bytes.extend(track);
+        bytes
+    }
+
+    #[test]
+    fn read_chords1() {
+        let track = [
+            0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20,
+            // C4 E4 G4, the last two by running status
+            0x00, 0x90, 60, 80, 0x00, 64, 80, 0x00, 67, 80,
+            // drums are ignored
+            0x00, 0x99, 36, 100,
+            // one beat later: C4 F4 A4, E4 released by a note on of velocity 0
+            0x60, 0x90, 64, 0, 0x00, 0x80, 67, 0, 0x00, 0x90, 65, 80, 0x00, 0x90, 69, 80,
+            0x60, 0x80, 60, 0, 0x00, 0x80, 65, 0, 0x00, 0x80, 69, 0,
+            0x00, 0xff, 0x2f, 0x00,
+        ];
+        let chords = read_chords(&smf(&track)).unwrap();
+        assert_eq!(chords.iter().map(|chord| chord.to_string()).collect::<Vec<String>>(), vec!["C4, E4, G4", "C4, F4, A4"]);
+        assert!(read_chords(b"RIFF").is_err());
+        assert!(read_chords(&smf(&[0x00, 0x90, 60])).is_err());
+    }
+
+    #[test]
+    fn read_chords2() {
+        // a data byte after a meta event has no status to repeat
+        let track = [0x00, 0x90, 60, 80, 0x00, 0xff, 0x01, 0x00, 0x00, 64, 80];
+        assert_eq!(read_chords(&smf(&track)).err().unwrap().to_string(), "<MidiError: Running status without a previous status>");
+        // more note ons of a note than a counter holds
+        let mut track = vec![0x00, 0x90, 60, 80];
+        for _ in 0..u16::MAX {
+            track.extend([0x00, 60, 80]);
+        }
+        track.extend([0x00, 0xff, 0x2f, 0x00]);
+        let chords = read_chords(&smf(&track)).unwrap();
+        assert_eq!(chords.iter().map(|chord| chord.to_string()).collect::<Vec<String>>(), vec!["C4"]);
+    }
+}
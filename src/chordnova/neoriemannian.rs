@@ -0,0 +1,267 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use itertools::Itertools;
+use crate::chordnova::chord::{CNChord, ParseCNChordError};
+use crate::chordnova::pitch::{Pitch, PitchClass};
+
+/// Chord qualities the transformations act on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quality {
+    Major,
+    Minor,
+    Dominant7,
+    Minor7,
+    HalfDiminished7,
+}
+
+impl Quality {
+    /// intervals above the root
+    fn intervals(&self) -> &'static [u8] {
+        match self {
+            Quality::Major => &[0, 4, 7],
+            Quality::Minor => &[0, 3, 7],
+            Quality::Dominant7 => &[0, 4, 7, 10],
+            Quality::Minor7 => &[0, 3, 7, 10],
+            Quality::HalfDiminished7 => &[0, 3, 6, 10],
+        }
+    }
+}
+
+/// A chord as a root and a quality, i.e. a node of the transformation space
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Harmony {
+    pub root: u8,
+    pub quality: Quality,
+}
+
+/// Neo-Riemannian transformations
+///
+/// The triadic ones exchange major and minor triads: P, L and R keep two common tones,
+/// N (Nebenverwandt, = RLP), S (slide, = LPR) and H (hexatonic pole, = LPL) are compounds of them.
+/// The seventh-chord variants keep three common tones:
+/// P7 exchanges C7 and Cm7, R7 exchanges C7 and Am7, L7 exchanges C7 and Eø7.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transformation {
+    P,
+    L,
+    R,
+    N,
+    S,
+    H,
+    P7,
+    R7,
+    L7,
+}
+
+impl fmt::Display for Transformation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseTransformationError {
+    msg: String,
+}
+
+impl fmt::Display for ParseTransformationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<ParseTransformationError: {}>", self.msg)
+    }
+}
+
+impl FromStr for Transformation {
+    type Err = ParseTransformationError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "P" => Ok(Transformation::P),
+            "L" => Ok(Transformation::L),
+            "R" => Ok(Transformation::R),
+            "N" => Ok(Transformation::N),
+            "S" => Ok(Transformation::S),
+            "H" => Ok(Transformation::H),
+            "P7" => Ok(Transformation::P7),
+            "R7" => Ok(Transformation::R7),
+            "L7" => Ok(Transformation::L7),
+            _ => Err(ParseTransformationError { msg: format!("Unknown transformation {}", s) })
+        }
+    }
+}
+
+/// Parse a compound sequence such as "PLR" or "P7R7"; it is applied from left to right
+#[allow(dead_code)]
+pub fn parse_transformations(s: &str) -> Result<Vec<Transformation>, ParseTransformationError> {
+    let mut transformations = vec![];
+    let mut chars = s.chars().filter(|c| !c.is_whitespace()).peekable();
+    while let Some(c) = chars.next() {
+        let token = match chars.peek() {
+            Some('7') => format!("{}{}", c, chars.next().unwrap()),
+            _ => c.to_string()
+        };
+        transformations.push(token.parse()?);
+    }
+    Ok(transformations)
+}
+
+impl Harmony {
+    /// Recognise the pitch-class content of `chord`
+    pub fn identify(chord: &CNChord) -> Option<Harmony> {
+        let pitch_classes = chord.get_distinct_pitch_classes().iter().map(|pitch_class| pitch_class.0).sorted().collect::<Vec<u8>>();
+        [Quality::Major, Quality::Minor, Quality::Dominant7, Quality::Minor7, Quality::HalfDiminished7].iter()
+            .cartesian_product(0..12u8)
+            .map(|(quality, root)| Harmony { root, quality: *quality })
+            .find(|harmony| harmony.pitch_classes().iter().copied().sorted().collect::<Vec<u8>>() == pitch_classes)
+    }
+
+    pub fn pitch_classes(&self) -> Vec<u8> {
+        self.quality.intervals().iter().map(|interval| (self.root + interval) % 12).collect()
+    }
+
+    /// `None` when `transformation` does not act on this quality
+    pub fn transform(&self, transformation: Transformation) -> Option<Harmony> {
+        let (offset, quality) = match (self.quality, transformation) {
+            (Quality::Major, Transformation::P) => (0, Quality::Minor),
+            (Quality::Major, Transformation::L) => (4, Quality::Minor),
+            (Quality::Major, Transformation::R) => (9, Quality::Minor),
+            (Quality::Major, Transformation::N) => (5, Quality::Minor),
+            (Quality::Major, Transformation::S) => (1, Quality::Minor),
+            (Quality::Major, Transformation::H) => (8, Quality::Minor),
+            (Quality::Minor, Transformation::P) => (0, Quality::Major),
+            (Quality::Minor, Transformation::L) => (8, Quality::Major),
+            (Quality::Minor, Transformation::R) => (3, Quality::Major),
+            (Quality::Minor, Transformation::N) => (7, Quality::Major),
+            (Quality::Minor, Transformation::S) => (11, Quality::Major),
+            (Quality::Minor, Transformation::H) => (4, Quality::Major),
+            (Quality::Dominant7, Transformation::P7) => (0, Quality::Minor7),
+            (Quality::Dominant7, Transformation::R7) => (9, Quality::Minor7),
+            (Quality::Dominant7, Transformation::L7) => (4, Quality::HalfDiminished7),
+            (Quality::Minor7, Transformation::P7) => (0, Quality::Dominant7),
+            (Quality::Minor7, Transformation::R7) => (3, Quality::Dominant7),
+            (Quality::HalfDiminished7, Transformation::L7) => (8, Quality::Dominant7),
+            _ => return None
+        };
+        Some(Harmony { root: (self.root + offset) % 12, quality })
+    }
+}
+
+/// Position of a pitch class on the Tonnetz: x counts perfect fifths, y major thirds,
+/// so that the pitch class is 7x + 4y (mod 12) with 0 <= x < 4 and 0 <= y < 3
+#[allow(dead_code)]
+pub fn tonnetz_coordinate(pitch_class: &PitchClass) -> (i8, i8) {
+    itertools::iproduct!(0..4i8, 0..3i8)
+        .find(|(x, y)| (7 * x + 4 * y) % 12 == pitch_class.0 as i8)
+        .unwrap()
+}
+
+/// signed movement from one pitch class to another, the shorter way round
+fn pitch_class_motion(from: u8, to: u8) -> i8 {
+    let up = ((to + 12 - from) % 12) as i8;
+    if up > 6 { up - 12 } else { up }
+}
+
+/// Apply `transformations` to `chord` from left to right, keeping common tones
+/// and moving every other voice by the smallest total movement
+#[allow(dead_code)]
+pub fn apply(chord: &CNChord, transformations: &[Transformation]) -> Result<CNChord, ParseCNChordError> {
+    let mut harmony = Harmony::identify(chord)
+        .ok_or_else(|| ParseCNChordError::new(format!("{} is not a triad or seventh chord", chord)))?;
+    let mut pitches = chord._pitches.clone();
+    for transformation in transformations {
+        let next = harmony.transform(*transformation)
+            .ok_or_else(|| ParseCNChordError::new(format!("{} does not act on {:?}", transformation, harmony.quality)))?;
+        let (old, new) = (harmony.pitch_classes(), next.pitch_classes());
+        let leaving = old.iter().filter(|pitch_class| !new.contains(pitch_class)).copied().collect::<Vec<u8>>();
+        let arriving = new.iter().filter(|pitch_class| !old.contains(pitch_class)).copied().collect::<Vec<u8>>();
+        let targets = arriving.iter().copied().permutations(arriving.len())
+            .min_by_key(|targets| leaving.iter().zip(targets).map(|(from, to)| pitch_class_motion(*from, *to).unsigned_abs()).sum::<u8>())
+            .unwrap();
+        let moves: HashMap<u8, i8> = leaving.iter().zip(targets).map(|(from, to)| (*from, pitch_class_motion(*from, to))).collect();
+        pitches = pitches.iter().map(|pitch| match moves.get(&pitch.get_pitch_class().0) {
+            Some(motion) => *pitch + *motion,
+            None => *pitch
+        }).collect::<Vec<Pitch>>();
+        harmony = next;
+    }
+    Ok(CNChord::from_notes(&pitches, false))
+}
+
+/// Fewest transformations from `alphabet` leading from `from` to `to`
+#[allow(dead_code)]
+pub fn shortest_path(from: Harmony, to: Harmony, alphabet: &[Transformation]) -> Option<Vec<Transformation>> {
+    let mut previous: HashMap<Harmony, (Harmony, Transformation)> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(harmony) = queue.pop_front() {
+        if harmony == to {
+            let mut path = vec![];
+            let mut cursor = harmony;
+            while let Some((before, transformation)) = previous.get(&cursor) {
+                path.push(*transformation);
+                cursor = *before;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for transformation in alphabet {
+            if let Some(next) = harmony.transform(*transformation) {
+                if next != from && !previous.contains_key(&next) {
+                    previous.insert(next, (harmony, *transformation));
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(chord: &str, transformations: &str) -> String {
+        apply(&CNChord::from_str(chord).unwrap(), &parse_transformations(transformations).unwrap()).unwrap().to_string()
+    }
+
+    #[test]
+    fn transformations1() {
+        assert_eq!(transform("C4 E4 G4", "P"), "C4, E-4, G4");
+        assert_eq!(transform("C4 E4 G4", "L"), "B3, E4, G4");
+        assert_eq!(transform("C4 E4 G4", "R"), "C4, E4, A4");
+        assert_eq!(transform("C4 E4 G4", "PLR"), "C4, F4, G#4");
+        assert_eq!(transform("C4 E4 G4", "H"), "B3, E-4, G#4");
+        assert_eq!(transform("E3 G3 C4", "N"), "F3, G#3, C4");
+        assert_eq!(transform("C4 E4 G4", "S"), "C#4, E4, G#4");
+        assert_eq!(transform("C4 E4 G4", "LL"), "C4, E4, G4");
+    }
+
+    #[test]
+    fn transformations2() {
+        assert_eq!(transform("C4 E4 G4 B-4", "P7"), "C4, E-4, G4, B-4");
+        assert_eq!(transform("C4 E4 G4 B-4", "R7"), "C4, E4, G4, A4");
+        assert_eq!(transform("C4 E4 G4 B-4", "L7"), "D4, E4, G4, B-4");
+        assert!(apply(&CNChord::from_str("C4 E4 G4").unwrap(), &[Transformation::P7]).is_err());
+        assert!(parse_transformations("PX").is_err());
+    }
+
+    #[test]
+    fn tonnetz1() {
+        assert_eq!(tonnetz_coordinate(&PitchClass(0)), (0, 0));
+        assert_eq!(tonnetz_coordinate(&PitchClass(7)), (1, 0));
+        assert_eq!(tonnetz_coordinate(&PitchClass(4)), (0, 1));
+        assert_eq!(tonnetz_coordinate(&PitchClass(9)), (3, 0));
+    }
+
+    #[test]
+    fn shortest_path1() {
+        let c_major = Harmony { root: 0, quality: Quality::Major };
+        let g_sharp_minor = Harmony { root: 8, quality: Quality::Minor };
+        let plr = [Transformation::P, Transformation::L, Transformation::R];
+        let path = shortest_path(c_major, g_sharp_minor, &plr).unwrap();
+        assert_eq!(path.len(), 3);
+        assert_eq!(path.iter().fold(c_major, |harmony, transformation| harmony.transform(*transformation).unwrap()), g_sharp_minor);
+        assert_eq!(shortest_path(c_major, g_sharp_minor, &[Transformation::H]).unwrap(), vec![Transformation::H]);
+        assert_eq!(shortest_path(c_major, c_major, &plr).unwrap(), vec![]);
+        let c_dominant = Harmony { root: 0, quality: Quality::Dominant7 };
+        assert!(shortest_path(c_major, c_dominant, &plr).is_none());
+    }
+}
@@ -0,0 +1,140 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use itertools::Itertools;
+use crate::chordnova::chord::CNChord;
+use crate::chordnova::generator::CNGenerator;
+use crate::chordnova::progression::Progression;
+
+/// Number of moves of a path
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Steps {
+    Exactly(usize),
+    Any,
+}
+
+/// sorted pitches of `chord`, identifying it regardless of voice order
+pub fn key(chord: &CNChord) -> Vec<u8> {
+    chord._pitches.iter().map(|pitch| pitch.0).sorted().collect()
+}
+
+/// Lower bound of the sv still needed from `chord` to `end`
+///
+/// Neither the lowest nor the highest note can move further in a step than its sv,
+/// so the distance of either is admissible for A*.
+fn heuristic(chord: &CNChord, end: &CNChord) -> u32 {
+    let (from, to) = (key(chord), key(end));
+    match (from.first(), from.last(), to.first(), to.last()) {
+        (Some(bass), Some(top), Some(end_bass), Some(end_top)) => u32::from(bass.abs_diff(*end_bass).max(top.abs_diff(*end_top))),
+        _ => 0
+    }
+}
+
+/// The path from `start` to `end` with the smallest total sv, every intermediate chord being a candidate of `generator`
+///
+/// A* search over the candidate graph; with `Steps::Exactly` a node is a chord together with the number of moves
+/// taken to reach it, and nodes that cannot reach `end` in the remaining moves under `vl_max` are pruned.
+#[allow(dead_code)]
+pub fn shortest_path(generator: &CNGenerator, start: &CNChord, end: &CNChord, steps: Steps) -> Option<Progression> {
+    shortest_path_where(generator, start, end, steps, |_, _, _| true)
+}
+
+/// `shortest_path` through the moves `accept(prev, chord, moves)` lets through only,
+/// `moves` being the number of moves taken once `chord` is reached
+pub fn shortest_path_where<F>(generator: &CNGenerator, start: &CNChord, end: &CNChord, steps: Steps, accept: F) -> Option<Progression>
+    where F: Fn(&CNChord, &CNChord, usize) -> bool {
+    let target = key(end);
+    let vl_max = u32::from(generator.config.vl_max);
+    // chord, moves taken, previous node
+    let mut nodes: Vec<(CNChord, usize, Option<usize>)> = vec![(start.clone(), 0, None)];
+    let mut index: HashMap<(Vec<u8>, usize), usize> = HashMap::from([((key(start), 0), 0)]);
+    let mut best: Vec<u32> = vec![0];
+    let mut heap = BinaryHeap::from([Reverse((heuristic(start, end), 0, 0))]);
+    while let Some(Reverse((_, cost, i))) = heap.pop() {
+        if cost > best[i] {
+            continue;
+        }
+        let moves = nodes[i].1;
+        let arrived = match steps {
+            Steps::Exactly(n) => moves == n,
+            Steps::Any => true,
+        };
+        if arrived && key(&nodes[i].0) == target {
+            let mut chords = vec![];
+            let mut node = Some(i);
+            while let Some(j) = node {
+                chords.push(nodes[j].0.clone());
+                node = nodes[j].2;
+            }
+            chords.reverse();
+            return Some(Progression { chords, cost: f64::from(cost) });
+        }
+        let remaining = match steps {
+            Steps::Exactly(n) if moves >= n => continue,
+            Steps::Exactly(n) => Some(n - moves - 1),
+            Steps::Any => None,
+        };
+        let prev = nodes[i].0.clone();
+        let candidates = generator.generate(&prev).into_iter()
+            .chain(generator.evaluate(&prev, end.clone(), false));
+        for candidate in candidates {
+            let estimate = heuristic(&candidate.chord, end);
+            if remaining.is_some_and(|remaining| estimate > remaining as u32 * vl_max) {
+                continue;
+            }
+            let next_cost = cost + u32::from(candidate.voice_leading.diff.sv);
+            let next_moves = if remaining.is_some() { moves + 1 } else { 0 };
+            if !accept(&prev, &candidate.chord, moves + 1) {
+                continue;
+            }
+            let j = *index.entry((key(&candidate.chord), next_moves)).or_insert_with(|| {
+                nodes.push((candidate.chord, next_moves, None));
+                best.push(u32::MAX);
+                nodes.len() - 1
+            });
+            if next_cost < best[j] {
+                best[j] = next_cost;
+                nodes[j].2 = Some(i);
+                heap.push(Reverse((next_cost + estimate, next_cost, j)));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::chordnova::generator::GeneratorConfig;
+    use crate::chordnova::pitch::Pitch;
+    use crate::chordnova::voiceleading::VoiceLeading;
+
+    #[test]
+    fn shortest_path1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let f_major: CNChord = CNChord::from_str("C4 F4 A4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() });
+        let path = shortest_path(&generator, &c_major, &f_major, Steps::Any).unwrap();
+        assert_eq!(path.chords.len(), 2);
+        assert_eq!(path.cost, 3.0);
+        let path = shortest_path(&generator, &c_major, &f_major, Steps::Exactly(3)).unwrap();
+        assert_eq!(path.chords.len(), 4);
+        assert_eq!(path.chords[3].to_string(), "C4, F4, A4");
+        assert_eq!(path.cost, 3.0);
+    }
+
+    #[test]
+    fn shortest_path2() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let f_sharp_major: CNChord = CNChord::from_str("F#4 A#4 C#5").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() });
+        // every voice has to rise by six semitones, two at a time
+        let path = shortest_path(&generator, &c_major, &f_sharp_major, Steps::Any).unwrap();
+        assert_eq!(path.cost, 18.0);
+        assert_eq!(path.chords.len(), 4);
+        assert!(shortest_path(&generator, &c_major, &f_sharp_major, Steps::Exactly(4)).unwrap().cost >= path.cost);
+        assert!(path.chords.windows(2).all(|pair| VoiceLeading::find(&pair[0], &pair[1], false).unwrap().max_movement() <= 2));
+        assert!(shortest_path(&generator, &c_major, &f_sharp_major, Steps::Exactly(2)).is_none());
+    }
+}
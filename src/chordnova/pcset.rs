@@ -0,0 +1,201 @@
+use std::fmt;
+use itertools::Itertools;
+use crate::chordnova::pitch::PitchClass;
+use crate::chordnova::util::iterable_to_str;
+
+/// A set of pitch classes, bit i standing for pitch class i
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PitchClassSet(pub u16);
+
+#[allow(dead_code)]
+impl PitchClassSet {
+    pub fn from_pitch_classes(pitch_classes: &[PitchClass]) -> Self {
+        PitchClassSet(pitch_classes.iter().fold(0, |set, pitch_class| set | (1 << (pitch_class.0 % 12))))
+    }
+
+    pub fn from_integers(pitch_classes: &[u8]) -> Self {
+        PitchClassSet(pitch_classes.iter().fold(0, |set, pitch_class| set | (1 << (pitch_class % 12))))
+    }
+
+    /// pitch classes in ascending order
+    pub fn pitch_classes(&self) -> Vec<u8> {
+        (0..12).filter(|pitch_class| self.contains(*pitch_class)).collect()
+    }
+
+    pub fn contains(&self, pitch_class: u8) -> bool {
+        self.0 & (1 << (pitch_class % 12)) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// image of every pitch class under x -> `factor` * x + `offset`
+    fn map(&self, factor: u8, offset: u8) -> Self {
+        PitchClassSet::from_integers(&self.pitch_classes().iter().map(|x| (factor * x + offset) % 12).collect::<Vec<u8>>())
+    }
+
+    /// Tn
+    pub fn transpose(&self, n: u8) -> Self {
+        self.map(1, n % 12)
+    }
+
+    /// TnI: x -> n - x
+    pub fn invert(&self, n: u8) -> Self {
+        self.map(11, n % 12)
+    }
+
+    /// Mm: x -> m * x; M5 and M7 are the ones that map set classes onto set classes
+    pub fn multiply(&self, m: u8) -> Self {
+        self.map(m % 12, 0)
+    }
+
+    pub fn complement(&self) -> Self {
+        PitchClassSet(!self.0 & 0xfff)
+    }
+
+    /// every Tn and TnI of this set
+    fn transformations(&self) -> impl Iterator<Item=PitchClassSet> + '_ {
+        (0..12).flat_map(move |n| [self.transpose(n), self.invert(n)])
+    }
+
+    /// Rahn's normal form: the most packed rotation, compared from the last pitch class backwards
+    pub fn normal_form(&self) -> Vec<u8> {
+        let pitch_classes = self.pitch_classes();
+        let size = pitch_classes.len();
+        (0..size)
+            .map(|start| (0..size).map(|i| pitch_classes[(start + i) % size]).collect::<Vec<u8>>())
+            .min_by_key(|rotation| (packing(rotation), rotation[0]))
+            .unwrap_or_default()
+    }
+
+    /// Rahn's prime form: the more packed of the normal forms of the set and its inversion, starting on 0
+    pub fn prime_form(&self) -> Vec<u8> {
+        [self.normal_form(), self.invert(0).normal_form()].iter()
+            .map(|form| form.iter().map(|x| (x + 12 - form[0]) % 12).collect::<Vec<u8>>())
+            .min_by_key(|form| packing(form))
+            .unwrap_or_default()
+    }
+
+    /// set class, represented by its prime form
+    pub fn set_class(&self) -> PitchClassSet {
+        PitchClassSet::from_integers(&self.prime_form())
+    }
+
+    /// number of intervals of every interval class from 1 to 6
+    pub fn interval_vector(&self) -> [u8; 6] {
+        let mut vector = [0u8; 6];
+        for (a, b) in self.pitch_classes().iter().tuple_combinations() {
+            let interval = b - a;
+            vector[usize::from(interval.min(12 - interval)) - 1] += 1;
+        }
+        vector
+    }
+
+    /// Same interval vector but different set classes
+    pub fn is_z_related(&self, other: &PitchClassSet) -> bool {
+        self.interval_vector() == other.interval_vector() && self.set_class() != other.set_class()
+    }
+
+    pub fn is_subset_of(&self, other: &PitchClassSet) -> bool {
+        self.0 & !other.0 == 0
+    }
+
+    pub fn is_superset_of(&self, other: &PitchClassSet) -> bool {
+        other.is_subset_of(self)
+    }
+
+    /// Some Tn or TnI of this set is a subset of `other`
+    pub fn is_abstract_subset_of(&self, other: &PitchClassSet) -> bool {
+        self.transformations().any(|set| set.is_subset_of(other))
+    }
+
+    pub fn is_abstract_superset_of(&self, other: &PitchClassSet) -> bool {
+        other.is_abstract_subset_of(self)
+    }
+
+    /// Forte's inclusion relation between set classes, in either direction
+    fn is_included_with(&self, other: &PitchClassSet) -> bool {
+        self.is_abstract_subset_of(other) || self.is_abstract_superset_of(other)
+    }
+
+    /// Forte's set complexes only relate sets of 3 to 9 pitch classes other than the nexus and its complement
+    fn is_complex_candidate(&self, nexus: &PitchClassSet) -> bool {
+        (3..=9).contains(&self.len()) && self.len() != nexus.len() && self.len() != nexus.complement().len()
+    }
+
+    /// Membership of Forte's set complex K(nexus):
+    /// this set or its complement is included in or includes the nexus or its complement
+    pub fn is_in_k_complex(&self, nexus: &PitchClassSet) -> bool {
+        self.is_complex_candidate(nexus)
+            && [*self, self.complement()].iter().any(|set| set.is_included_with(nexus) || set.is_included_with(&nexus.complement()))
+    }
+
+    /// Membership of Forte's subcomplex Kh(nexus):
+    /// this set is included in or includes both the nexus and its complement
+    pub fn is_in_kh_complex(&self, nexus: &PitchClassSet) -> bool {
+        self.is_complex_candidate(nexus) && self.is_included_with(nexus) && self.is_included_with(&nexus.complement())
+    }
+}
+
+/// Distances of the pitch classes from the first one, last pitch class first; smaller is more packed
+fn packing(form: &[u8]) -> Vec<u8> {
+    form.iter().rev().map(|x| (x + 12 - form[0]) % 12).collect()
+}
+
+impl fmt::Display for PitchClassSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", iterable_to_str(self.pitch_classes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transformations1() {
+        let c_major = PitchClassSet::from_integers(&[0, 4, 7]);
+        assert_eq!(c_major.transpose(2).to_string(), "[2, 6, 9]");
+        assert_eq!(c_major.invert(7).to_string(), "[0, 3, 7]");
+        assert_eq!(PitchClassSet::from_integers(&[0, 1, 2]).multiply(5).to_string(), "[0, 5, 10]");
+        assert_eq!(c_major.complement().len(), 9);
+    }
+
+    #[test]
+    fn prime_form1() {
+        assert_eq!(PitchClassSet::from_integers(&[4, 7, 0]).normal_form(), vec![0, 4, 7]);
+        assert_eq!(PitchClassSet::from_integers(&[9, 0, 4]).prime_form(), vec![0, 3, 7]);
+        assert_eq!(PitchClassSet::from_integers(&[0, 4, 7, 10]).prime_form(), vec![0, 2, 5, 8]);
+        assert_eq!(PitchClassSet::from_integers(&[11, 0, 2, 4, 5, 7, 9]).normal_form(), vec![11, 0, 2, 4, 5, 7, 9]);
+        // Rahn and Forte differ on 5-20; Forte packs to the left and gets [0, 1, 3, 7, 8]
+        assert_eq!(PitchClassSet::from_integers(&[5, 6, 8, 0, 1]).prime_form(), vec![0, 1, 5, 6, 8]);
+    }
+
+    #[test]
+    fn z_relation1() {
+        let z15 = PitchClassSet::from_integers(&[0, 1, 4, 6]);
+        let z29 = PitchClassSet::from_integers(&[0, 1, 3, 7]);
+        assert_eq!(z15.interval_vector(), [1, 1, 1, 1, 1, 1]);
+        assert!(z15.is_z_related(&z29));
+        assert!(!z15.is_z_related(&z15.transpose(3)));
+    }
+
+    #[test]
+    fn complex1() {
+        let diatonic = PitchClassSet::from_integers(&[0, 2, 4, 5, 7, 9, 11]);
+        let minor_triad = PitchClassSet::from_integers(&[2, 5, 9]);
+        let cluster = PitchClassSet::from_integers(&[0, 1, 2]);
+        assert!(minor_triad.is_abstract_subset_of(&diatonic));
+        assert!(!minor_triad.transpose(1).is_subset_of(&diatonic));
+        assert!(minor_triad.is_in_k_complex(&diatonic));
+        assert!(minor_triad.is_in_kh_complex(&diatonic));
+        assert!(!cluster.is_in_k_complex(&diatonic));
+        assert!(!diatonic.is_in_k_complex(&diatonic));
+    }
+}
@@ -83,7 +83,7 @@ impl FromStr for Accidental {
 
 #[derive(Debug)]
 #[derive(Clone)]
-pub struct PitchClass(u8);
+pub struct PitchClass(pub u8);
 
 impl PartialEq for PitchClass {
     fn eq(&self, other: &Self) -> bool {
@@ -94,15 +94,15 @@ impl PartialEq for PitchClass {
 
 impl PitchClass {
     pub fn is_natural(&self) -> bool {
-        return vec![0, 2, 4, 5, 7, 9, 11].contains(&self.0);
+        [0, 2, 4, 5, 7, 9, 11].contains(&self.0)
     }
 
     pub fn is_sharpable(&self) -> bool {
-        return vec![1, 6, 8].contains(&self.0);
+        [1, 6, 8].contains(&self.0)
     }
 
     pub fn is_flatable(&self) -> bool {
-        return vec![3, 10].contains(&self.0);
+        [3, 10].contains(&self.0)
     }
 
     pub fn to_step_name(&self) -> (Stepname, Accidental) {
@@ -131,10 +131,10 @@ pub fn convert_ps_to_step(midi_note_number: u8) -> (Stepname, Accidental, Option
     let pitch_class = PitchClass(midi_note_number % 12);
     let (step_name, accidental) = pitch_class.to_step_name();
     let octive = midi_note_number / 12;
-    return (step_name, accidental, match octive {
+    (step_name, accidental, match octive {
         1..=u8::MAX => Some(octive - 1),
         0 => None
-    });
+    })
 }
 
 
@@ -146,9 +146,7 @@ impl Add<i8> for Pitch {
     type Output = Pitch;
 
     fn add(self, rhs: i8) -> Self::Output {
-        Self {
-            0: u8::try_from(i8::try_from(self.0).unwrap() + rhs).unwrap()
-        }
+        Self(u8::try_from(i8::try_from(self.0).unwrap() + rhs).unwrap())
     }
 }
 
@@ -156,9 +154,7 @@ impl Sub<i8> for Pitch {
     type Output = Pitch;
 
     fn sub(self, rhs: i8) -> Self::Output {
-        Self {
-            0: u8::try_from(i8::try_from(self.0).unwrap() - rhs).unwrap()
-        }
+        Self(u8::try_from(i8::try_from(self.0).unwrap() - rhs).unwrap())
     }
 }
 
@@ -166,7 +162,7 @@ impl Copy for Pitch {}
 
 impl Clone for Pitch {
     fn clone(&self) -> Self {
-        Pitch(self.0)
+        *self
     }
 }
 
@@ -183,22 +179,28 @@ impl Pitch {
         })
     }
 
+    /// frequency in Hz, A4 being 440 Hz
+    pub fn frequency(&self) -> f64 {
+        440f64 * 2f64.powf((f64::from(self.0) - 69f64) / 12f64)
+    }
+
     pub fn get_pitch_class(&self) -> PitchClass {
         PitchClass(self.0 % 12)
     }
 
     pub fn get_nearest_pitch_by_pitch_class(&self, pitch_classes: &Vec<PitchClass>) -> Pitch {
-        for (offset, direction) in iproduct!((0..12), vec![-1i8, 1i8]) {
+        for (offset, direction) in iproduct!((0..12), vec![-1i16, 1i16]) {
             // println!("Trying {} | {}", direction, offset);
-            let selected_pitch = *self + direction * offset;
-            match (*pitch_classes).iter().position(|pitch_class| *pitch_class == selected_pitch.get_pitch_class()) {
-                Some(_) => {
-                    // println!("{}, {:?} == {:?}", selected_pitch, selected_pitch.get_pitch_class(), pitch_classes);
-                    return selected_pitch;
-                }
-                None => {}
+            // candidates outside of the MIDI range are skipped; the other direction always stays inside
+            let selected_note = i16::from(self.0) + direction * offset;
+            if !(0..=127).contains(&selected_note) {
+                continue;
+            }
+            let selected_pitch = Pitch(selected_note as u8);
+            if (*pitch_classes).iter().any(|pitch_class| *pitch_class == selected_pitch.get_pitch_class()) {
+                // println!("{}, {:?} == {:?}", selected_pitch, selected_pitch.get_pitch_class(), pitch_classes);
+                return selected_pitch;
             }
-            continue;
         }
         unreachable!()
     }
@@ -258,7 +260,7 @@ impl FromStr for Pitch {
                             Ok(Pitch::from_stepname(stepname, accidental, octive))
                         }
                         rule => Err(ParsePitchError {
-                            msg: String::from(format!("Unknown rule {:?}", rule))
+                            msg: format!("Unknown rule {:?}", rule)
                         })
                     },
                     None => Err(ParsePitchError {
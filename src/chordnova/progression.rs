@@ -0,0 +1,17 @@
+use std::fmt;
+use itertools::Itertools;
+use crate::chordnova::chord::CNChord;
+
+/// A sequence of chords, together with the cost the search assigned to it
+#[derive(Clone)]
+pub struct Progression {
+    pub chords: Vec<CNChord>,
+    /// sum of the cost of every step, smaller is better
+    pub cost: f64,
+}
+
+impl fmt::Display for Progression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<Progression: {}, cost: {:.2}>", self.chords.iter().map(|chord| format!("[{}]", chord)).join(" -> "), self.cost)
+    }
+}
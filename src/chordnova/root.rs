@@ -0,0 +1,120 @@
+use itertools::Itertools;
+use crate::chordnova::chord::CNChord;
+use crate::chordnova::pitch::{Pitch, PitchClass};
+
+/// Algorithms for r, the root of a chord
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootAlgorithm {
+    /// Hindemith's interval roots: the root of the best-ranked interval, the lowest one on ties
+    Hindemith,
+    /// Parncutt's (1988) virtual pitch model: the pitch class best supported by the root-support weights
+    Parncutt,
+}
+
+/// The root of a chord and how strongly the chord implies it, from 0 to 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootEstimate {
+    pub root: PitchClass,
+    pub salience: f32,
+}
+
+/// Hindemith's intervals from best to worst root definition, with whether the root is the lower note.
+/// The tritone has no root.
+const HINDEMITH_RANKING: [(u8, bool); 10] = [
+    (7, true),
+    (5, false),
+    (4, true),
+    (8, false),
+    (3, true),
+    (9, false),
+    (2, false),
+    (10, true),
+    (1, false),
+    (11, true),
+];
+
+/// Root-support weights of Parncutt (1988), indexed by the interval above the root
+const PARNCUTT_WEIGHTS: [u8; 12] = [10, 0, 1, 0, 3, 0, 0, 5, 0, 0, 2, 0];
+
+impl RootAlgorithm {
+    pub fn find_root(&self, chord: &CNChord) -> Option<RootEstimate> {
+        if chord.t_size() == 0 {
+            return None;
+        }
+        match self {
+            RootAlgorithm::Hindemith => hindemith_root(&chord._pitches.iter().copied().sorted().collect::<Vec<Pitch>>()),
+            RootAlgorithm::Parncutt => parncutt_root(chord),
+        }
+    }
+}
+
+fn hindemith_root(pitches: &[Pitch]) -> Option<RootEstimate> {
+    if pitches.iter().map(|pitch| pitch.get_pitch_class()).all_equal() {
+        return Some(RootEstimate { root: pitches[0].get_pitch_class(), salience: 1f32 });
+    }
+    pitches.iter().tuple_combinations()
+        .filter_map(|(lower, upper)| {
+            let rank = HINDEMITH_RANKING.iter().position(|(interval, _)| *interval == (upper.0 - lower.0) % 12)?;
+            let root = if HINDEMITH_RANKING[rank].1 { lower } else { upper };
+            Some((rank, *lower, root.get_pitch_class()))
+        })
+        .min_by_key(|(rank, lower, _)| (*rank, *lower))
+        .map(|(rank, _, root)| RootEstimate {
+            root,
+            salience: (HINDEMITH_RANKING.len() - rank) as f32 / HINDEMITH_RANKING.len() as f32,
+        })
+}
+
+fn parncutt_root(chord: &CNChord) -> Option<RootEstimate> {
+    let pitch_classes = chord.get_distinct_pitch_classes();
+    let weights = (0..12u8).map(|root| {
+        pitch_classes.iter().map(|pitch_class| u32::from(PARNCUTT_WEIGHTS[usize::from((pitch_class.0 + 12 - root) % 12)])).sum::<u32>()
+    }).collect::<Vec<u32>>();
+    best_supported_root(chord, &weights)
+}
+
+/// the pitch class with the largest weight, its share of all weights being the salience
+fn best_supported_root(chord: &CNChord, weights: &[u32]) -> Option<RootEstimate> {
+    let total = weights.iter().sum::<u32>();
+    if total == 0 {
+        return None;
+    }
+    // among equally supported roots, the one sounding lowest wins
+    let best = weights.iter().max()?;
+    let root = chord._pitches.iter().sorted()
+        .map(|pitch| pitch.get_pitch_class())
+        .find(|pitch_class| weights[usize::from(pitch_class.0)] == *best)
+        .unwrap_or_else(|| PitchClass(weights.iter().position(|weight| weight == best).unwrap() as u8));
+    Some(RootEstimate { root, salience: *best as f32 / total as f32 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn root(algorithm: RootAlgorithm, chord: &str) -> u8 {
+        algorithm.find_root(&CNChord::from_str(chord).unwrap()).unwrap().root.0
+    }
+
+    #[test]
+    fn hindemith1() {
+        assert_eq!(root(RootAlgorithm::Hindemith, "E3 G3 C4"), 0);
+        assert_eq!(root(RootAlgorithm::Hindemith, "G3 C4 E4"), 0);
+        assert_eq!(root(RootAlgorithm::Hindemith, "C4 E4 G4 B-4"), 0);
+        // the lowest fifth decides
+        assert_eq!(root(RootAlgorithm::Hindemith, "C3 G3 D4 A4"), 0);
+        assert_eq!(root(RootAlgorithm::Hindemith, "E3 B3 C4 G4"), 4);
+        assert!(RootAlgorithm::Hindemith.find_root(&CNChord::from_str("C4 F#4").unwrap()).is_none());
+    }
+
+    #[test]
+    fn parncutt1() {
+        assert_eq!(root(RootAlgorithm::Parncutt, "E3 G3 C4"), 0);
+        assert_eq!(root(RootAlgorithm::Parncutt, "A3 C4 E4"), 9);
+        assert_eq!(root(RootAlgorithm::Parncutt, "G3 B3 D4 F4"), 7);
+        let estimate = RootAlgorithm::Parncutt.find_root(&CNChord::from_str("C4 E4 G4").unwrap()).unwrap();
+        assert!(estimate.salience > RootAlgorithm::Parncutt.find_root(&CNChord::from_str("C4 E-4 G-4 A4").unwrap()).unwrap().salience);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::chordnova::chord::CNChord;
+use crate::chordnova::generator::{Candidate, CNGenerator};
+use crate::chordnova::metric::Metric;
+use crate::chordnova::progression::Progression;
+use crate::chordnova::util::SplitMix64;
+
+/// Parameters of the sampler
+pub struct SamplerConfig {
+    /// score of a candidate, smaller being more likely; the cost of the generator's prior is added to it
+    pub score: Metric,
+    /// spread of the softmax; 0 always takes the best-scoring candidate
+    pub temperature: f64,
+    pub seed: u64,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        SamplerConfig {
+            score: Metric::VoiceLeading,
+            temperature: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Draws candidates of a generator at random, with probability proportional to exp(-score / temperature)
+///
+/// The same seed, generator and starting chord always give the same draws.
+pub struct Sampler {
+    pub config: SamplerConfig,
+    rng: SplitMix64,
+}
+
+impl Sampler {
+    #[allow(dead_code)]
+    pub fn new(config: SamplerConfig) -> Self {
+        let rng = SplitMix64::new(config.seed);
+        Sampler { config, rng }
+    }
+
+    /// a random candidate following `prev` with its score; `None` if there is no candidate
+    pub fn sample(&mut self, generator: &CNGenerator, prev: &CNChord) -> Option<(f64, Candidate)> {
+        let mut scored = generator.generate(prev).into_iter()
+            .map(|candidate| (self.config.score.evaluate(prev, &candidate) + generator.prior_cost(prev, &candidate), candidate))
+            .collect::<Vec<(f64, Candidate)>>();
+        let best = scored.iter().map(|(score, _)| *score).min_by(|a, b| a.total_cmp(b))?;
+        if self.config.temperature <= 0.0 {
+            let i = scored.iter().position(|(score, _)| *score == best).unwrap();
+            return Some(scored.swap_remove(i));
+        }
+        // shifted by the best score so that the weights cannot all underflow
+        let weights = scored.iter().map(|(score, _)| (-(score - best) / self.config.temperature).exp()).collect::<Vec<f64>>();
+        let mut threshold = self.rng.next_f64() * weights.iter().sum::<f64>();
+        let i = weights.iter().position(|weight| {
+            threshold -= weight;
+            threshold < 0.0
+        }).unwrap_or(weights.len() - 1);
+        Some(scored.swap_remove(i))
+    }
+
+    /// a random progression of up to `steps` chords after `start`, its cost being the sum of the scores;
+    /// shorter if a chord has no candidate
+    #[allow(dead_code)]
+    pub fn sample_progression(&mut self, generator: &CNGenerator, start: &CNChord, steps: usize) -> Progression {
+        let mut progression = Progression { chords: vec![start.clone()], cost: 0.0 };
+        for _ in 0..steps {
+            match self.sample(generator, progression.chords.last().unwrap()) {
+                Some((score, candidate)) => {
+                    progression.cost += score;
+                    progression.chords.push(candidate.chord);
+                }
+                None => break
+            }
+        }
+        progression
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::chordnova::generator::GeneratorConfig;
+    use crate::chordnova::markov::{MarkovModel, Prior};
+    use crate::chordnova::pitch::Pitch;
+
+    #[test]
+    fn sample1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, ..Default::default() });
+        let sample = |seed: u64, temperature: f64| Sampler::new(SamplerConfig { temperature, seed, ..Default::default() })
+            .sample_progression(&generator, &c_major, 4)
+            .chords.iter().map(|chord| chord.to_string()).collect::<Vec<String>>();
+        assert_eq!(sample(7, 2.0), sample(7, 2.0));
+        assert_ne!(sample(7, 2.0), sample(8, 2.0));
+        assert_eq!(sample(7, 2.0).len(), 5);
+        // without temperature nothing moves, staying costs nothing
+        assert!(sample(7, 0.0).iter().all(|chord| chord == "C4, E4, G4"));
+    }
+
+    #[test]
+    fn prior1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let mut model = MarkovModel::default();
+        model.train_text("C4 E4 G4 | C4 F4 A4 | C4 E4 G4").unwrap();
+        let generator = CNGenerator::new(GeneratorConfig { lowest: Pitch(55), highest: Pitch(72), min_voices: 3, max_voices: 3, vl_max: 2, prior: Some(Prior { model, weight: 10.0 }), ..Default::default() });
+        let progression = Sampler::new(SamplerConfig { temperature: 0.0, ..Default::default() }).sample_progression(&generator, &c_major, 2);
+        assert_eq!(progression.chords.iter().map(|chord| chord.to_string()).collect::<Vec<String>>(), vec!["C4, E4, G4", "C4, F4, A4", "C4, E4, G4"]);
+    }
+}
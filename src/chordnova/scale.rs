@@ -0,0 +1,99 @@
+use crate::chordnova::pcset::PitchClassSet;
+use crate::chordnova::pitch::{Pitch, PitchClass};
+
+const MAJOR: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+const MELODIC_MINOR: [u8; 7] = [0, 2, 3, 5, 7, 9, 11];
+const HARMONIC_MINOR: [u8; 7] = [0, 2, 3, 5, 7, 8, 11];
+
+/// Scales above a tonic
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleType {
+    Ionian,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Aeolian,
+    Locrian,
+    /// mode of the (ascending) melodic minor scale, 1 being the scale itself
+    MelodicMinor(usize),
+    /// mode of the harmonic minor scale, 1 being the scale itself
+    HarmonicMinor(usize),
+    /// octatonic scale starting with a half step
+    OctatonicHalfWhole,
+    /// octatonic scale starting with a whole step
+    OctatonicWholeHalf,
+    WholeTone,
+    /// Cohn's hexatonic scale, alternating half steps and minor thirds
+    Hexatonic,
+    /// any set of pitch classes, measured from the tonic
+    Custom(PitchClassSet),
+}
+
+/// the `mode`-th rotation of a seven-note scale, measured from its new tonic
+fn mode_of(parent: &[u8; 7], mode: usize) -> Vec<u8> {
+    let start = parent[(mode + 6) % 7];
+    parent.iter().map(|degree| (degree + 12 - start) % 12).collect()
+}
+
+impl ScaleType {
+    /// pitch classes above a tonic of 0
+    pub fn pitch_class_set(&self) -> PitchClassSet {
+        match self {
+            ScaleType::Ionian => PitchClassSet::from_integers(&mode_of(&MAJOR, 1)),
+            ScaleType::Dorian => PitchClassSet::from_integers(&mode_of(&MAJOR, 2)),
+            ScaleType::Phrygian => PitchClassSet::from_integers(&mode_of(&MAJOR, 3)),
+            ScaleType::Lydian => PitchClassSet::from_integers(&mode_of(&MAJOR, 4)),
+            ScaleType::Mixolydian => PitchClassSet::from_integers(&mode_of(&MAJOR, 5)),
+            ScaleType::Aeolian => PitchClassSet::from_integers(&mode_of(&MAJOR, 6)),
+            ScaleType::Locrian => PitchClassSet::from_integers(&mode_of(&MAJOR, 7)),
+            ScaleType::MelodicMinor(mode) => PitchClassSet::from_integers(&mode_of(&MELODIC_MINOR, *mode)),
+            ScaleType::HarmonicMinor(mode) => PitchClassSet::from_integers(&mode_of(&HARMONIC_MINOR, *mode)),
+            ScaleType::OctatonicHalfWhole => PitchClassSet::from_integers(&[0, 1, 3, 4, 6, 7, 9, 10]),
+            ScaleType::OctatonicWholeHalf => PitchClassSet::from_integers(&[0, 2, 3, 5, 6, 8, 9, 11]),
+            ScaleType::WholeTone => PitchClassSet::from_integers(&[0, 2, 4, 6, 8, 10]),
+            ScaleType::Hexatonic => PitchClassSet::from_integers(&[0, 1, 4, 5, 8, 9]),
+            ScaleType::Custom(set) => *set,
+        }
+    }
+}
+
+/// A scale on a given tonic, e.g. a key
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scale {
+    pub tonic: PitchClass,
+    pub scale_type: ScaleType,
+}
+
+#[allow(dead_code)]
+impl Scale {
+    pub fn new(tonic: PitchClass, scale_type: ScaleType) -> Self {
+        Scale { tonic, scale_type }
+    }
+
+    pub fn pitch_class_set(&self) -> PitchClassSet {
+        self.scale_type.pitch_class_set().transpose(self.tonic.0)
+    }
+
+    pub fn contains(&self, pitch: &Pitch) -> bool {
+        self.pitch_class_set().contains(pitch.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales1() {
+        assert_eq!(Scale::new(PitchClass(2), ScaleType::Dorian).pitch_class_set(), Scale::new(PitchClass(0), ScaleType::Ionian).pitch_class_set());
+        assert_eq!(Scale::new(PitchClass(9), ScaleType::Aeolian).pitch_class_set().pitch_classes(), vec![0, 2, 4, 5, 7, 9, 11]);
+        // the altered scale is the seventh mode of melodic minor
+        assert_eq!(ScaleType::MelodicMinor(7).pitch_class_set().pitch_classes(), vec![0, 1, 3, 4, 6, 8, 10]);
+        // Phrygian dominant is the fifth mode of harmonic minor
+        assert_eq!(ScaleType::HarmonicMinor(5).pitch_class_set().pitch_classes(), vec![0, 1, 4, 5, 7, 8, 10]);
+        let hexatonic = Scale::new(PitchClass(3), ScaleType::Hexatonic);
+        assert!(hexatonic.contains(&Pitch(60)) && hexatonic.contains(&Pitch(64)) && !hexatonic.contains(&Pitch(62)));
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use itertools::Itertools;
+use crate::chordnova::pcset::PitchClassSet;
+
+/// Forte's similarity relations between sets of the same size
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForteRelation {
+    /// no interval-class vector entry in common
+    R0,
+    /// four entries in common, the other two interchanged
+    R1,
+    /// four entries in common, the other two not interchanged
+    R2,
+    /// a common subset with one pitch class fewer, up to Tn / TnI
+    Rp,
+}
+
+/// Forte's relations that hold between `a` and `b`; empty when the sizes differ
+#[allow(dead_code)]
+pub fn forte_relations(a: &PitchClassSet, b: &PitchClassSet) -> Vec<ForteRelation> {
+    if a.len() != b.len() || a.is_empty() {
+        return vec![];
+    }
+    let (va, vb) = (a.interval_vector(), b.interval_vector());
+    let differing = (0..6).filter(|i| va[*i] != vb[*i]).collect::<Vec<usize>>();
+    let mut relations = vec![];
+    match differing.len() {
+        6 => relations.push(ForteRelation::R0),
+        2 if va[differing[0]] == vb[differing[1]] && va[differing[1]] == vb[differing[0]] => relations.push(ForteRelation::R1),
+        2 => relations.push(ForteRelation::R2),
+        _ => {}
+    }
+    let shares_subset = a.pitch_classes().iter()
+        .map(|pitch_class| PitchClassSet(a.0 & !(1 << pitch_class)))
+        .any(|subset| subset.is_abstract_subset_of(b));
+    if shares_subset {
+        relations.push(ForteRelation::Rp);
+    }
+    relations
+}
+
+/// Morris's SIM: sum of the differences of the interval-class vectors; 0 for identical vectors
+pub fn sim(a: &PitchClassSet, b: &PitchClassSet) -> u32 {
+    a.interval_vector().iter().zip(b.interval_vector().iter())
+        .map(|(x, y)| u32::from(x.abs_diff(*y)))
+        .sum()
+}
+
+/// EMB(X, set) for every set class X with at least two pitch classes
+fn embeddings(set: &PitchClassSet) -> HashMap<PitchClassSet, u32> {
+    let mut counts = HashMap::new();
+    let pitch_classes = set.pitch_classes();
+    for size in 2..=pitch_classes.len() {
+        for subset in pitch_classes.iter().combinations(size) {
+            let subset = PitchClassSet::from_integers(&subset.into_iter().copied().collect::<Vec<u8>>());
+            *counts.entry(subset.set_class()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Similarity measures between the pitch-class contents of two chords, from 0 (unrelated) to 1
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimilarityMeasure {
+    /// 1 - Morris's ASIM, i.e. SIM divided by the number of intervals of both sets
+    Sim,
+    /// Lewin's REL over the embeddings of all set classes of two or more pitch classes
+    Rel,
+    /// Rahn's ATMEMB: share of the embeddings in set classes both sets contain
+    Atmemb,
+    /// cosine of the angle between the interval-class vectors
+    Cosine,
+}
+
+impl SimilarityMeasure {
+    pub fn similarity(&self, a: &PitchClassSet, b: &PitchClassSet) -> f64 {
+        match self {
+            SimilarityMeasure::Sim => {
+                let total = a.interval_vector().iter().chain(b.interval_vector().iter()).map(|x| u32::from(*x)).sum::<u32>();
+                match total {
+                    0 => 1.0,
+                    _ => 1.0 - f64::from(sim(a, b)) / f64::from(total)
+                }
+            }
+            SimilarityMeasure::Rel => {
+                let (ea, eb) = (embeddings(a), embeddings(b));
+                let (ta, tb) = (ea.values().sum::<u32>(), eb.values().sum::<u32>());
+                if ta == 0 || tb == 0 {
+                    return 0.0;
+                }
+                ea.iter()
+                    .filter_map(|(set_class, x)| eb.get(set_class).map(|y| f64::from(x * y).sqrt()))
+                    .sum::<f64>() / f64::from(ta * tb).sqrt()
+            }
+            SimilarityMeasure::Atmemb => {
+                let (ea, eb) = (embeddings(a), embeddings(b));
+                let total = ea.values().sum::<u32>() + eb.values().sum::<u32>();
+                if total == 0 {
+                    return 0.0;
+                }
+                let mutual = ea.iter()
+                    .filter_map(|(set_class, x)| eb.get(set_class).map(|y| x + y))
+                    .sum::<u32>();
+                f64::from(mutual) / f64::from(total)
+            }
+            SimilarityMeasure::Cosine => {
+                let (va, vb) = (a.interval_vector(), b.interval_vector());
+                let dot = va.iter().zip(vb.iter()).map(|(x, y)| f64::from(*x) * f64::from(*y)).sum::<f64>();
+                let norm = |v: &[u8; 6]| v.iter().map(|x| f64::from(*x).powi(2)).sum::<f64>().sqrt();
+                match norm(&va) * norm(&vb) {
+                    0.0 => 0.0,
+                    product => dot / product
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forte1() {
+        // 4-Z15 and 4-Z29 share their vector and the trichord 3-5
+        let z15 = PitchClassSet::from_integers(&[0, 1, 4, 6]);
+        let z29 = PitchClassSet::from_integers(&[0, 1, 3, 7]);
+        assert_eq!(forte_relations(&z15, &z29), vec![ForteRelation::Rp]);
+        // 3-11 <001110> and 3-7 <011010>
+        let minor = PitchClassSet::from_integers(&[0, 3, 7]);
+        let three_seven = PitchClassSet::from_integers(&[0, 2, 5]);
+        assert_eq!(forte_relations(&minor, &three_seven), vec![ForteRelation::R1, ForteRelation::Rp]);
+        // 3-1 <210000> differs from 3-11 in five entries
+        let cluster = PitchClassSet::from_integers(&[0, 1, 2]);
+        assert!(forte_relations(&cluster, &minor).is_empty());
+        // 4-1 <321000> and 4-5 <210111>, both containing 3-1
+        let tetrachord = PitchClassSet::from_integers(&[0, 1, 2, 3]);
+        let four_five = PitchClassSet::from_integers(&[0, 1, 2, 6]);
+        assert_eq!(forte_relations(&tetrachord, &four_five), vec![ForteRelation::R0, ForteRelation::Rp]);
+    }
+
+    #[test]
+    fn measures1() {
+        let c_major = PitchClassSet::from_integers(&[0, 4, 7]);
+        let a_minor = PitchClassSet::from_integers(&[9, 0, 4]);
+        let c_dominant = PitchClassSet::from_integers(&[0, 4, 7, 10]);
+        let cluster = PitchClassSet::from_integers(&[0, 1, 2]);
+        assert_eq!(sim(&c_major, &a_minor), 0);
+        for measure in [SimilarityMeasure::Sim, SimilarityMeasure::Rel, SimilarityMeasure::Atmemb, SimilarityMeasure::Cosine] {
+            assert!((measure.similarity(&c_major, &a_minor) - 1.0).abs() < 1e-9, "{:?}", measure);
+            assert!(measure.similarity(&c_major, &c_dominant) > measure.similarity(&c_major, &cluster), "{:?}", measure);
+        }
+        assert_eq!(SimilarityMeasure::Atmemb.similarity(&c_major, &cluster), 0.0);
+    }
+}
@@ -0,0 +1,117 @@
+use itertools::Itertools;
+use crate::chordnova::chord::CNChord;
+
+/// A model of t, the tension of a chord
+pub trait TensionModel {
+    fn tension(&self, chord: &CNChord) -> f64;
+}
+
+/// Partials of a single note as (frequency ratio to the fundamental, amplitude)
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrum {
+    pub partials: Vec<(f64, f64)>,
+}
+
+#[allow(dead_code)]
+impl Spectrum {
+    /// `count` harmonics, the amplitude of each one being `rolloff` times the previous one
+    pub fn harmonic(count: usize, rolloff: f64) -> Self {
+        Spectrum {
+            partials: (0..count).map(|k| ((k + 1) as f64, rolloff.powi(k as i32))).collect()
+        }
+    }
+
+    /// every partial of every note of `chord` as (frequency, amplitude)
+    fn partials_of(&self, chord: &CNChord) -> Vec<(f64, f64)> {
+        chord._pitches.iter()
+            .flat_map(|pitch| self.partials.iter().map(move |(ratio, amplitude)| (pitch.frequency() * ratio, *amplitude)))
+            .collect()
+    }
+}
+
+impl Default for Spectrum {
+    fn default() -> Self {
+        Spectrum::harmonic(6, 0.88)
+    }
+}
+
+/// Sensory roughness of Plomp and Levelt, as parametrised by Sethares
+#[allow(dead_code)]
+pub struct SetharesRoughness {
+    pub spectrum: Spectrum,
+}
+
+impl TensionModel for SetharesRoughness {
+    fn tension(&self, chord: &CNChord) -> f64 {
+        const D_STAR: f64 = 0.24;
+        const S1: f64 = 0.0207;
+        const S2: f64 = 18.96;
+        const B1: f64 = 3.51;
+        const B2: f64 = 5.75;
+        self.spectrum.partials_of(chord).iter().tuple_combinations()
+            .map(|((f1, a1), (f2, a2))| {
+                let s = D_STAR / (S1 * f1.min(*f2) + S2);
+                let distance = (f2 - f1).abs();
+                a1.min(*a2) * ((-B1 * s * distance).exp() - (-B2 * s * distance).exp())
+            })
+            .sum()
+    }
+}
+
+/// Roughness of Hutchinson and Knopoff, with Parncutt's approximation of the critical-band curve
+#[allow(dead_code)]
+pub struct HutchinsonKnopoffRoughness {
+    pub spectrum: Spectrum,
+}
+
+impl TensionModel for HutchinsonKnopoffRoughness {
+    fn tension(&self, chord: &CNChord) -> f64 {
+        let partials = self.spectrum.partials_of(chord);
+        let energy = partials.iter().map(|(_, amplitude)| amplitude * amplitude).sum::<f64>();
+        if energy == 0.0 {
+            return 0.0;
+        }
+        partials.iter().tuple_combinations()
+            .map(|((f1, a1), (f2, a2))| {
+                let critical_bandwidth = 1.72 * ((f1 + f2) / 2.0).powf(0.65);
+                let y = (f2 - f1).abs() / critical_bandwidth;
+                let g = match y < 1.2 {
+                    true => (y / 0.25 * (1.0 - y / 0.25).exp()).powi(2),
+                    false => 0.0
+                };
+                a1 * a2 * g
+            })
+            .sum::<f64>() / energy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn tensions(model: &dyn TensionModel, chords: &[&str]) -> Vec<f64> {
+        chords.iter().map(|chord| model.tension(&CNChord::from_str(chord).unwrap())).collect()
+    }
+
+    #[test]
+    fn roughness1() {
+        let models: Vec<Box<dyn TensionModel>> = vec![
+            Box::new(SetharesRoughness { spectrum: Spectrum::default() }),
+            Box::new(HutchinsonKnopoffRoughness { spectrum: Spectrum::default() }),
+        ];
+        for model in models {
+            let values = tensions(model.as_ref(), &["C4 C5", "C4 G4", "C4 D4", "C4 C#4"]);
+            assert!(values.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn roughness2() {
+        // pure tones an octave apart do not interact
+        let model = SetharesRoughness { spectrum: Spectrum::harmonic(1, 1.0) };
+        assert!(model.tension(&CNChord::from_str("C4 C5").unwrap()) < 1e-3);
+        assert!(model.tension(&CNChord::from_str("C2 D-2").unwrap()) < model.tension(&CNChord::from_str("C5 D-5").unwrap()));
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+use itertools::Itertools;
+use crate::chordnova::chord::{CNChord, ChordDiff, ParseCNChordError, VoiceMatcher};
+use crate::chordnova::pitch::Pitch;
+use crate::chordnova::util::iterable_to_str;
+
+/// A matched voice leading: `from[i]` is matched to `to[i]`.
+///
+/// The movement is measured on the matched pitches, while crossings and overlaps are found
+/// on the voices, numbered in the order they are given, e.g. the order a chord is written in.
+pub struct VoiceLeading {
+    pub from: Vec<Pitch>,
+    pub to: Vec<Pitch>,
+    /// v / sv / norm of the movement
+    pub diff: ChordDiff,
+    /// (i, j), i < j: voices i and j swap places, one starting below the other and ending above it
+    pub crossings: Vec<(usize, usize)>,
+    /// (i, i + 1): one of two neighbouring voices moves past the previous position of the other
+    pub overlaps: Vec<(usize, usize)>,
+}
+
+impl VoiceLeading {
+    /// Voice leading whose voices are the matched pairs themselves
+    pub fn new(pairs: Vec<(Pitch, Pitch)>) -> Self {
+        let voices = pairs.clone();
+        VoiceLeading::with_voices(pairs, &voices)
+    }
+
+    /// Voice leading matched as `pairs`, every voice moving as given by `voices`
+    pub fn with_voices(pairs: Vec<(Pitch, Pitch)>, voices: &[(Pitch, Pitch)]) -> Self {
+        let (from, to): (Vec<Pitch>, Vec<Pitch>) = pairs.into_iter().unzip();
+        let diff = ChordDiff::new(from.iter().zip(to.iter()).map(|(f, t)| i16::from(t.0) - i16::from(f.0)).collect());
+        let (start, end): (Vec<Pitch>, Vec<Pitch>) = voices.iter().copied().unzip();
+        let crossings = (0..start.len()).tuple_combinations()
+            .filter(|(i, j)| (start[*i] < start[*j] && end[*i] > end[*j]) || (start[*i] > start[*j] && end[*i] < end[*j]))
+            .collect();
+        let overlaps = (0..start.len()).tuple_windows()
+            .filter(|(i, j)| {
+                let (lower, upper) = if start[*i] <= start[*j] { (*i, *j) } else { (*j, *i) };
+                start[lower] < start[upper] && (end[lower] > start[upper] || end[upper] < start[lower])
+            })
+            .collect();
+        VoiceLeading {
+            from,
+            to,
+            diff,
+            crossings,
+            overlaps,
+        }
+    }
+
+    /// Pair the voices of two chords of the same size in the order they were written
+    #[allow(dead_code)]
+    pub fn from_voices(from: &CNChord, to: &CNChord) -> Result<Self, ParseCNChordError> {
+        match from.diff(to) {
+            Ok(_) if from.t_size() == to.t_size() => Ok(VoiceLeading::new(from._pitches.iter().copied().zip(to._pitches.iter().copied()).collect())),
+            Ok(_) => Err(ParseCNChordError::new(format!("Cannot pair {} voices with {} voices", from.t_size(), to.t_size()))),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Match the voices of `from` and `to` with `CNChord::find_vec`
+    pub fn find(from: &CNChord, to: &CNChord, in_substitution: bool) -> Result<Self, ParseCNChordError> {
+        VoiceLeading::find_with(from, to, in_substitution, VoiceMatcher::default())
+    }
+
+    /// Match the voices of `from` and `to` with `CNChord::find_vec_with`
+    ///
+    /// Matching sorts both chords, so the voices of two chords of the same size
+    /// are taken in the order they are written instead; substitution revoices `to`.
+    pub fn find_with(from: &CNChord, to: &CNChord, in_substitution: bool, matcher: VoiceMatcher) -> Result<Self, ParseCNChordError> {
+        let (matched_from, matched_to) = from.find_vec_with(to, in_substitution, matcher)?;
+        let pairs = matched_from._pitches.into_iter().zip(matched_to._pitches).collect();
+        match !in_substitution && from.t_size() == to.t_size() {
+            true => Ok(VoiceLeading::with_voices(pairs, &from._pitches.iter().copied().zip(to._pitches.iter().copied()).collect::<Vec<(Pitch, Pitch)>>())),
+            false => Ok(VoiceLeading::new(pairs))
+        }
+    }
+
+    /// largest movement of a single voice
+    pub fn max_movement(&self) -> u16 {
+        self.diff.diff_vec.iter().map(|x| x.unsigned_abs()).max().unwrap_or(0)
+    }
+
+    pub fn has_crossing(&self) -> bool {
+        !self.crossings.is_empty()
+    }
+
+    pub fn has_overlap(&self) -> bool {
+        !self.overlaps.is_empty()
+    }
+}
+
+impl fmt::Display for VoiceLeading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<VoiceLeading: {} -> {}, sv: {}, crossings: {}, overlaps: {}>",
+               iterable_to_str(&self.from),
+               iterable_to_str(&self.to),
+               self.diff.sv,
+               iterable_to_str(self.crossings.iter().map(|(i, j)| format!("{}-{}", i, j))),
+               iterable_to_str(self.overlaps.iter().map(|(i, j)| format!("{}-{}", i, j))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn crossing1() {
+        let c_major: CNChord = CNChord::from_str("C3 G3 E4 C5").unwrap();
+        let g_major: CNChord = CNChord::from_str("B2 G4 D4 G3").unwrap();
+        let voice_leading = VoiceLeading::from_voices(&c_major, &g_major).unwrap();
+        assert_eq!(voice_leading.crossings, vec![(1, 2), (1, 3), (2, 3)]);
+        assert_eq!(voice_leading.overlaps, vec![(1, 2), (2, 3)]);
+        assert_eq!(voice_leading.diff.sv, 32);
+    }
+
+    #[test]
+    fn overlap1() {
+        let c_major: CNChord = CNChord::from_str("C4 E4 G4").unwrap();
+        let f_major: CNChord = CNChord::from_str("F4 A4 C5").unwrap();
+        let voice_leading = VoiceLeading::from_voices(&c_major, &f_major).unwrap();
+        assert!(!voice_leading.has_crossing());
+        assert_eq!(voice_leading.overlaps, vec![(0, 1), (1, 2)]);
+        let voice_leading = VoiceLeading::find(&c_major, &f_major, true).unwrap();
+        assert!(!voice_leading.has_overlap());
+        assert_eq!(voice_leading.to_string(), "<VoiceLeading: [C4, E4, G4] -> [C4, F4, A4], sv: 3, crossings: [], overlaps: []>");
+    }
+
+    #[test]
+    fn crossing2() {
+        // written in voice order: the tenor E4 sits above the alto G3
+        let crossed: CNChord = CNChord::from_str("C3 E4 G3 C5").unwrap();
+        let f_major: CNChord = CNChord::from_str("C3 F4 A3 C5").unwrap();
+        let voice_leading = VoiceLeading::find(&crossed, &f_major, false).unwrap();
+        assert_eq!(voice_leading.diff.diff_vec, vec![0, 2, 1, 0]);
+        assert!(!voice_leading.has_crossing());
+        // the two voices change places, although both chords sound the same notes
+        let uncrossed: CNChord = CNChord::from_str("C3 G3 E4 C5").unwrap();
+        let voice_leading = VoiceLeading::find(&crossed, &uncrossed, false).unwrap();
+        assert_eq!(voice_leading.crossings, vec![(1, 2)]);
+        assert_eq!(voice_leading.diff.sv, 0);
+    }
+}
@@ -0,0 +1,339 @@
+use itertools::Itertools;
+use crate::chordnova::chord::CNChord;
+use crate::chordnova::chordsymbol::ChordSymbol;
+use crate::chordnova::ensemble::VoiceRange;
+use crate::chordnova::pcset::PitchClassSet;
+use crate::chordnova::pitch::{Pitch, PitchClass};
+
+/// Spacing of the upper voices, i.e. every voice above the bass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Any,
+    /// no pitch class of the set fits between two adjacent upper voices
+    Closed,
+    /// some pitch class of the set fits between two adjacent upper voices
+    Open,
+}
+
+/// Conditions a voicing of a pitch-class set has to meet
+#[derive(Clone)]
+pub struct VoicingConstraints {
+    /// pitch classes the voicing is made of
+    pub pitch_classes: PitchClassSet,
+    /// lowest note of a voicing
+    pub lowest: Pitch,
+    /// highest note of a voicing
+    pub highest: Pitch,
+    /// least number of notes of a voicing
+    pub min_voices: usize,
+    /// most number of notes of a voicing
+    pub max_voices: usize,
+    /// whether every pitch class has to appear
+    pub complete: bool,
+    /// pitch classes that may appear more than once
+    pub doublable: PitchClassSet,
+    /// most number of notes doubling a pitch class already present
+    pub max_doublings: usize,
+    /// pitch class of the lowest note
+    pub bass: Option<PitchClass>,
+    /// largest interval between adjacent voices in semitones
+    pub max_spacing: Option<u8>,
+    pub position: Position,
+    /// range of every voice from the bass upwards; when given, voicings have exactly this many voices
+    pub voice_ranges: Option<Vec<VoiceRange>>,
+    /// pitches every voicing has to contain
+    pub required: Vec<Pitch>,
+    /// pitches of which at least `min_common` have to appear, e.g. those of the previous chord
+    pub common_with: Vec<Pitch>,
+    pub min_common: usize,
+    /// highest note of every voicing, e.g. a melody note
+    pub soprano: Option<Pitch>,
+}
+
+impl Default for VoicingConstraints {
+    fn default() -> Self {
+        VoicingConstraints {
+            pitch_classes: PitchClassSet(0xfff),
+            lowest: Pitch(48),
+            highest: Pitch(72),
+            min_voices: 3,
+            max_voices: 4,
+            complete: false,
+            doublable: PitchClassSet(0xfff),
+            max_doublings: usize::MAX,
+            bass: None,
+            max_spacing: None,
+            position: Position::Any,
+            voice_ranges: None,
+            required: vec![],
+            common_with: vec![],
+            min_common: 0,
+            soprano: None,
+        }
+    }
+}
+
+impl VoicingConstraints {
+    /// complete voicings of `symbol`, with its bass note in the bass if it names one
+    #[allow(dead_code)]
+    pub fn for_chord_symbol(symbol: &ChordSymbol) -> Self {
+        VoicingConstraints {
+            pitch_classes: symbol.pitch_class_set(),
+            complete: true,
+            bass: symbol.bass.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Every voicing meeting a set of constraints, by number of voices and then from the bottom up
+///
+/// Voicings are enumerated lazily by a depth-first search over the notes of the set within range,
+/// pruning a partial voicing as soon as it breaks a constraint or can no longer be completed.
+pub struct Voicings {
+    constraints: VoicingConstraints,
+    /// notes of the set within range, ascending
+    pitches: Vec<Pitch>,
+    /// number of voices currently enumerated
+    size: usize,
+    max_size: usize,
+    /// indices into `pitches` of the partial voicing
+    stack: Vec<usize>,
+}
+
+impl Voicings {
+    pub fn new(constraints: VoicingConstraints) -> Self {
+        let pitches = (constraints.lowest.0..=constraints.highest.0)
+            .map(Pitch)
+            .filter(|pitch| constraints.pitch_classes.contains(pitch.0))
+            .collect();
+        let (size, max_size) = match &constraints.voice_ranges {
+            Some(voice_ranges) => (voice_ranges.len(), voice_ranges.len()),
+            None => (constraints.min_voices.max(1), constraints.max_voices)
+        };
+        Voicings { constraints, pitches, size, max_size, stack: vec![] }
+    }
+
+    fn contains(&self, pitch: &Pitch) -> bool {
+        self.stack.iter().any(|i| self.pitches[*i] == *pitch)
+    }
+
+    fn pitch_class_set(&self) -> PitchClassSet {
+        PitchClassSet::from_integers(&self.stack.iter().map(|i| self.pitches[*i].0).collect::<Vec<u8>>())
+    }
+
+    /// first index from `from` on that may extend the partial voicing
+    fn next_valid(&self, from: usize) -> Option<usize> {
+        let present = self.pitch_class_set();
+        let doublings = self.stack.len() - present.len();
+        let remaining = self.size - self.stack.len() - 1;
+        for i in from..self.pitches.len() {
+            let pitch = self.pitches[i];
+            if let Some(voice_range) = self.constraints.voice_ranges.as_ref().map(|voice_ranges| voice_ranges[self.stack.len()]) {
+                if pitch.0 > voice_range.highest.0 {
+                    return None;
+                }
+                if !voice_range.contains(&pitch) {
+                    continue;
+                }
+            }
+            if let Some(&last) = self.stack.last() {
+                let last = self.pitches[last];
+                if self.constraints.max_spacing.is_some_and(|max_spacing| pitch.0 - last.0 > max_spacing) {
+                    return None;
+                }
+                // adjacent upper voices have to be adjacent notes of the set
+                if self.constraints.position == Position::Closed && self.stack.len() >= 2 && i > self.stack[self.stack.len() - 1] + 1 {
+                    return None;
+                }
+            } else if self.constraints.bass.as_ref().is_some_and(|bass| bass.0 != pitch.0 % 12) {
+                continue;
+            }
+            if present.contains(pitch.0) && (!self.constraints.doublable.contains(pitch.0) || doublings >= self.constraints.max_doublings) {
+                continue;
+            }
+            if let Some(soprano) = self.constraints.soprano {
+                if pitch > soprano {
+                    return None;
+                }
+                if remaining == 0 && pitch != soprano {
+                    continue;
+                }
+            }
+            if self.constraints.required.iter().any(|required| *required < pitch && !self.contains(required)) {
+                return None;
+            }
+            if self.constraints.required.iter().unique_by(|pitch| pitch.0).filter(|required| **required > pitch && !self.contains(required)).count() > remaining {
+                continue;
+            }
+            if self.constraints.min_common > 0 {
+                let common = self.constraints.common_with.iter().unique_by(|pitch| pitch.0).filter(|common| **common == pitch || self.contains(common)).count();
+                let available = self.constraints.common_with.iter().unique_by(|pitch| pitch.0).filter(|common| **common > pitch).count();
+                if common + available.min(remaining) < self.constraints.min_common {
+                    continue;
+                }
+            }
+            if self.constraints.complete {
+                let covered = PitchClassSet(present.0 | (1 << (pitch.0 % 12)));
+                if self.constraints.pitch_classes.len() - covered.len() > remaining {
+                    continue;
+                }
+            }
+            return Some(i);
+        }
+        None
+    }
+
+    fn is_open(&self) -> bool {
+        self.stack.windows(2).skip(1).any(|pair| pair[1] > pair[0] + 1)
+    }
+}
+
+impl Iterator for Voicings {
+    type Item = CNChord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.size > self.max_size || self.size == 0 {
+                return None;
+            }
+            let extended = self.stack.len() < self.size && match self.next_valid(self.stack.last().map_or(0, |i| i + 1)) {
+                Some(i) => {
+                    self.stack.push(i);
+                    true
+                }
+                None => false
+            };
+            if !extended {
+                loop {
+                    match self.stack.pop() {
+                        Some(i) => if let Some(j) = self.next_valid(i + 1) {
+                            self.stack.push(j);
+                            break;
+                        },
+                        None => {
+                            self.size += 1;
+                            break;
+                        }
+                    }
+                }
+            }
+            if self.stack.len() == self.size && (self.constraints.position != Position::Open || self.is_open()) {
+                let pitches = self.stack.iter().map(|i| self.pitches[*i]).collect::<Vec<Pitch>>();
+                return Some(CNChord::from_notes(&pitches, false));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::*;
+    use crate::chordnova::ensemble::Ensemble;
+
+    #[test]
+    fn voicings1() {
+        let constraints = VoicingConstraints {
+            pitch_classes: PitchClassSet::from_integers(&[0, 4, 7]),
+            lowest: Pitch(60),
+            highest: Pitch(72),
+            min_voices: 3,
+            max_voices: 3,
+            complete: true,
+            ..Default::default()
+        };
+        let voicings = Voicings::new(constraints).map(|chord| chord.to_string()).collect::<Vec<String>>();
+        assert_eq!(voicings, vec!["C4, E4, G4", "E4, G4, C5"]);
+    }
+
+    #[test]
+    fn voicings2() {
+        let symbol = ChordSymbol::from_str("C/E").unwrap();
+        let constraints = VoicingConstraints {
+            lowest: Pitch(52),
+            highest: Pitch(76),
+            min_voices: 4,
+            max_voices: 4,
+            ..VoicingConstraints::for_chord_symbol(&symbol)
+        };
+        let voicings = Voicings::new(constraints.clone()).collect::<Vec<CNChord>>();
+        assert!(!voicings.is_empty());
+        for chord in &voicings {
+            assert_eq!(chord._pitches[0].0 % 12, 4);
+            assert_eq!(chord.get_pitch_class_set(), PitchClassSet::from_integers(&[0, 4, 7]));
+        }
+        let no_doubled_third = Voicings::new(VoicingConstraints { doublable: PitchClassSet::from_integers(&[0, 7]), ..constraints.clone() });
+        for chord in no_doubled_third {
+            assert_eq!(chord._pitches.iter().filter(|pitch| pitch.get_pitch_class().0 == 4).count(), 1);
+        }
+        let closed = Voicings::new(VoicingConstraints { position: Position::Closed, ..constraints.clone() })
+            .map(|chord| chord.to_string()).collect::<Vec<String>>();
+        assert!(closed.contains(&"E3, C4, E4, G4".to_string()));
+        assert!(!closed.contains(&"E3, G3, E4, C5".to_string()));
+        let open = Voicings::new(VoicingConstraints { position: Position::Open, ..constraints })
+            .collect::<Vec<CNChord>>();
+        assert_eq!(open.len() + closed.len(), voicings.len());
+    }
+
+    #[test]
+    fn voicings3() {
+        let constraints = VoicingConstraints {
+            pitch_classes: PitchClassSet::from_integers(&[0, 4, 7]),
+            lowest: Pitch(48),
+            highest: Pitch(84),
+            min_voices: 2,
+            max_voices: 3,
+            max_spacing: Some(5),
+            ..Default::default()
+        };
+        let voicings = Voicings::new(constraints).collect::<Vec<CNChord>>();
+        assert_eq!(voicings[0].to_string(), "C3, E3");
+        assert!(voicings.iter().all(|chord| chord._pitches.windows(2).all(|pair| pair[1].0 - pair[0].0 <= 5)));
+        assert!(voicings.iter().any(|chord| chord.t_size() == 3));
+    }
+
+    #[test]
+    fn voicings4() {
+        let constraints = VoicingConstraints {
+            pitch_classes: PitchClassSet::from_integers(&[0, 4, 7]),
+            lowest: Pitch(36),
+            highest: Pitch(84),
+            complete: true,
+            voice_ranges: Some(Ensemble::Satb.voice_ranges()),
+            ..Default::default()
+        };
+        let voicings = Voicings::new(constraints).collect::<Vec<CNChord>>();
+        assert!(voicings.iter().any(|chord| chord.to_string() == "C3, G3, E4, C5"));
+        assert!(voicings.iter().all(|chord| chord.t_size() == 4 && chord._pitches[1].0 <= 69 && chord._pitches[0].0 >= 40));
+    }
+
+    #[test]
+    fn voicings5() {
+        let constraints = VoicingConstraints {
+            lowest: Pitch(48),
+            highest: Pitch(60),
+            min_voices: 3,
+            max_voices: 3,
+            required: vec![Pitch(48)],
+            common_with: vec![Pitch(52), Pitch(55), Pitch(60)],
+            min_common: 2,
+            ..Default::default()
+        };
+        let voicings = Voicings::new(constraints).collect::<Vec<CNChord>>();
+        assert!(voicings.iter().any(|chord| chord.to_string() == "C3, E3, G3"));
+        assert!(voicings.iter().all(|chord| chord._pitches[0] == Pitch(48)));
+        assert!(voicings.iter().all(|chord| chord.common_notes(&CNChord::from_notes(&[Pitch(52), Pitch(55), Pitch(60)], false)) >= 2));
+        assert_eq!(voicings.len(), 3);
+        let constraints = VoicingConstraints {
+            pitch_classes: PitchClassSet::from_integers(&[0, 4, 7]),
+            lowest: Pitch(48),
+            highest: Pitch(72),
+            soprano: Some(Pitch(64)),
+            ..Default::default()
+        };
+        let voicings = Voicings::new(constraints).map(|chord| chord.to_string()).collect::<Vec<String>>();
+        assert_eq!(voicings[0], "C3, E3, E4");
+        assert!(voicings.iter().all(|chord| chord.ends_with("E4")));
+    }
+}
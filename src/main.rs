@@ -1,8 +1,33 @@
 mod chordnova {
+    pub mod analyser;
+    pub mod beamsearch;
     pub mod chord;
+    pub mod chordsymbol;
+    pub mod consonance;
+    pub mod counterpoint;
+    pub mod curve;
+    pub mod cycle;
+    pub mod dft;
+    pub mod ensemble;
+    pub mod figuredbass;
+    pub mod generator;
+    pub mod markov;
+    pub mod metric;
+    pub mod midi;
+    pub mod neoriemannian;
+    pub mod pathfinder;
+    pub mod pcset;
     pub mod pitch;
     pub mod pitchparser;
+    pub mod progression;
+    pub mod root;
+    pub mod sampler;
+    pub mod scale;
+    pub mod similarity;
+    pub mod tension;
     pub mod util;
+    pub mod voiceleading;
+    pub mod voicing;
 }
 
 // use crate::chordnova::chord::{CNChord, OverflowState};